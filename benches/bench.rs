@@ -2,7 +2,7 @@
 
 extern crate test;
 
-use hyperloglog::HyperLogLog;
+use hyperloglog::{HyperLogLog, Precision};
 use test::{black_box, Bencher};
 
 #[bench]
@@ -20,6 +20,36 @@ fn insert(b: &mut Bencher) {
     });
 }
 
+#[bench]
+fn insert_many(b: &mut Bencher) {
+    let mut hll = HyperLogLog::<i32>::new();
+    b.iter(|| hll.insert_many(0..100_000));
+}
+
+#[bench]
+fn insert_hash_p16(b: &mut Bencher) {
+    let hashes = hashes();
+    let mut hll = HyperLogLog::<i32>::with_precision(Precision::P16);
+    b.iter(|| {
+        for h in &hashes {
+            hll.insert_hash(*h);
+        }
+    });
+}
+
+#[bench]
+fn insert_hashes_p16(b: &mut Bencher) {
+    let hashes = hashes();
+    let mut hll = HyperLogLog::<i32>::with_precision(Precision::P16);
+    b.iter(|| hll.insert_hashes(&hashes));
+}
+
+fn hashes() -> Vec<u64> {
+    (0..100_000u64)
+        .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15).rotate_left(17))
+        .collect()
+}
+
 #[bench]
 fn len(b: &mut Bencher) {
     let mut hll = HyperLogLog::<i32>::new();
@@ -52,3 +82,20 @@ macro_rules! bench_merge {
 bench_merge!(try_merge);
 
 bench_merge!(try_merge_from);
+
+#[bench]
+fn try_merge_from_p14(b: &mut Bencher) {
+    let mut hll1 = HyperLogLog::<i32>::with_precision(Precision::P14);
+    for i in 0..750_000 {
+        hll1.insert(&i);
+    }
+    let mut hll2 = HyperLogLog::<i32>::with_precision(Precision::P14);
+    for i in 250_000..1_000_000 {
+        hll2.insert(&i);
+    }
+    b.iter(|| {
+        let mut hll = hll1.clone();
+        hll.try_merge_from(&hll2).unwrap();
+        hll
+    })
+}
@@ -0,0 +1,108 @@
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+
+/// Nibble marking a register whose value is stored in the exceptions.
+const EXCEPTION: u8 = 0xf;
+
+/// Dense register values, stored on 4 bits each as a difference to a shared offset.
+///
+/// Values too far above the offset are stored in a separate map of exceptions. The offset
+/// is the smallest register value, so it rises once every register has risen past it.
+/// As register values stay close to each other, exceptions are rare.
+#[derive(Clone, Debug)]
+pub struct CompactValues {
+    /// Two registers per byte, the even one in the low nibble.
+    nibbles: Vec<u8>,
+    offset: u8,
+    /// Number of registers whose value is the offset.
+    num_at_offset: usize,
+    exceptions: BTreeMap<u32, u8>,
+}
+
+impl CompactValues {
+    /// Creates `len` registers set to zero, `len` being a multiple of 2.
+    pub fn with_len(len: usize) -> Self {
+        debug_assert_eq!(len % 2, 0);
+        Self {
+            nibbles: vec![0; len / 2],
+            offset: 0,
+            num_at_offset: len,
+            exceptions: BTreeMap::new(),
+        }
+    }
+
+    #[inline]
+    fn nibble(&self, index: usize) -> u8 {
+        (self.nibbles[index / 2] >> (4 * (index % 2))) & 0xf
+    }
+
+    #[inline]
+    fn set_nibble(&mut self, index: usize, nibble: u8) {
+        let shift = 4 * (index % 2);
+        let byte = &mut self.nibbles[index / 2];
+        *byte = (*byte & !(0xf << shift)) | (nibble << shift);
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> u8 {
+        match self.nibble(index) {
+            EXCEPTION => self.exceptions[&(index as u32)],
+            delta => self.offset + delta,
+        }
+    }
+
+    /// Raises the value of a register, which must not be lower than its current value.
+    pub fn raise(&mut self, index: usize, value: u8) {
+        let nibble = self.nibble(index);
+        if nibble == EXCEPTION {
+            self.exceptions.insert(index as u32, value);
+            return;
+        }
+        if nibble == value - self.offset {
+            return;
+        }
+        self.store(index, value);
+        if nibble == 0 {
+            self.num_at_offset -= 1;
+            if self.num_at_offset == 0 {
+                self.rebase();
+            }
+        }
+    }
+
+    /// Stores a register value above the offset, in its nibble or in the exceptions.
+    #[inline]
+    fn store(&mut self, index: usize, value: u8) {
+        let delta = value - self.offset;
+        if delta >= EXCEPTION {
+            self.set_nibble(index, EXCEPTION);
+            self.exceptions.insert(index as u32, value);
+        } else {
+            self.set_nibble(index, delta);
+        }
+    }
+
+    /// Raises the offset to the smallest register value, once no register holds the
+    /// current offset.
+    fn rebase(&mut self) {
+        while self.num_at_offset == 0 {
+            self.offset += 1;
+            for index in 0..self.nibbles.len() * 2 {
+                match self.nibble(index) {
+                    EXCEPTION => {
+                        let value = self.exceptions[&(index as u32)];
+                        if value - self.offset < EXCEPTION {
+                            self.exceptions.remove(&(index as u32));
+                            self.store(index, value);
+                        }
+                    }
+                    nibble => {
+                        self.set_nibble(index, nibble - 1);
+                        if nibble == 1 {
+                            self.num_at_offset += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
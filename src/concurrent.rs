@@ -0,0 +1,414 @@
+use alloc::boxed::Box;
+use core::{
+    borrow::Borrow,
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
+use crate::{
+    error::TryMergeError,
+    estimate::Estimate,
+    estimator::{EstimationStrategy, Estimator},
+    histogram::Histogram,
+    hyperloglog::{check_merge_params, split_hash, DefaultBuildHasher, HyperLogLog},
+    identity::HasherIdentity,
+    precision::Precision,
+    storage::RegisterStorage,
+    xxhash::hash_bytes,
+};
+
+/// A hyperloglog that may be inserted into from several threads at once, without locking.
+///
+/// Registers are atomic bytes, raised with [`AtomicU8::fetch_max`]. Estimates may be read
+/// at any time, without stopping writers: they reflect the registers at the time they are
+/// read, which may include some of the concurrent insertions.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+///
+/// use hyperloglog::{ConcurrentHyperLogLog, HyperLogLog};
+///
+/// let hll = ConcurrentHyperLogLog::<u32>::new();
+/// thread::scope(|s| {
+///     for t in 0..4 {
+///         let hll = &hll;
+///         s.spawn(move || {
+///             for i in 0..25_000 {
+///                 hll.insert(&(t * 25_000 + i));
+///             }
+///         });
+///     }
+/// });
+/// assert!((50_000..150_000).contains(&hll.len()));
+///
+/// let hll: HyperLogLog<u32> = hll.into();
+/// assert!((50_000..150_000).contains(&hll.len()));
+/// ```
+pub struct ConcurrentHyperLogLog<T, S = DefaultBuildHasher, E = EstimationStrategy>
+where
+    T: ?Sized,
+{
+    registers: Box<[AtomicU8]>,
+    precision: Precision,
+    hash_builder: S,
+    estimator: E,
+    // Values are only hashed, so sharing the hyperloglog does not require `T: Sync`.
+    phantom: PhantomData<fn(&T)>,
+}
+
+impl<T, S, E> ConcurrentHyperLogLog<T, S, E>
+where
+    T: ?Sized,
+    S: Default,
+    E: Default,
+{
+    /// Creates a new empty hyperloglog with the default precision.
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_precision(Precision::default())
+    }
+
+    /// Creates a new empty hyperloglog with the given precision.
+    #[inline]
+    #[must_use]
+    pub fn with_precision(precision: Precision) -> Self {
+        Self::with_precision_and_hasher(precision, S::default())
+    }
+}
+
+impl<T, S, E> ConcurrentHyperLogLog<T, S, E>
+where
+    T: ?Sized,
+    E: Default,
+{
+    /// Creates a new empty hyperloglog with the default precision and the given hasher
+    /// to hash keys.
+    #[inline]
+    #[must_use]
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::with_precision_and_hasher(Precision::default(), hasher)
+    }
+
+    /// Creates a new empty hyperloglog with the given precision and the given hasher to hash keys.
+    #[must_use]
+    pub fn with_precision_and_hasher(precision: Precision, hasher: S) -> Self {
+        Self {
+            registers: (0..1 << precision.get())
+                .map(|_| AtomicU8::new(0))
+                .collect(),
+            precision,
+            hash_builder: hasher,
+            estimator: E::default(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, S, E> ConcurrentHyperLogLog<T, S, E>
+where
+    T: ?Sized,
+{
+    /// Returns the precision of the hyperloglog.
+    #[inline]
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    /// Returns a reference to the hyperloglog's [`BuildHasher`].
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    /// Returns a reference to the hyperloglog's [`Estimator`].
+    #[inline]
+    pub fn estimator(&self) -> &E {
+        &self.estimator
+    }
+
+    /// Adds raw bytes to the hyperloglog, regardless of its hasher.
+    ///
+    /// See [`HyperLogLog::insert_bytes`] for details.
+    #[inline]
+    pub fn insert_bytes(&self, bytes: &[u8]) {
+        self.insert_hash(hash_bytes(bytes));
+    }
+
+    /// Adds a hash value to the hyperloglog.
+    ///
+    /// See [`HyperLogLog::insert_hash`] for details.
+    #[inline]
+    pub fn insert_hash(&self, h: u64) {
+        let (j, rho) = split_hash(self.precision, h);
+        let register = &self.registers[j];
+        // Most insertions leave the register unchanged, which a load detects without
+        // taking ownership of the cache line.
+        if register.load(Ordering::Relaxed) < rho {
+            register.fetch_max(rho, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the histogram of register values, as currently read.
+    pub fn histogram(&self) -> Histogram {
+        Histogram::from_values(
+            self.precision,
+            self.registers
+                .iter()
+                .map(|register| register.load(Ordering::Relaxed)),
+        )
+    }
+
+    /// Estimates the number of different elements with the given estimator, regardless of
+    /// the hyperloglog's own estimator.
+    #[inline]
+    pub fn estimate_with<F>(&self, estimator: &F) -> f64
+    where
+        F: Estimator + ?Sized,
+    {
+        estimator.estimate(&self.histogram())
+    }
+
+    /// Clears the hyperloglog, removing all values.
+    #[inline]
+    pub fn clear(&mut self) {
+        for register in self.registers.iter_mut() {
+            *register.get_mut() = 0;
+        }
+    }
+
+    /// Returns `true` if the hyperloglog contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.registers
+            .iter()
+            .all(|register| register.load(Ordering::Relaxed) == 0)
+    }
+
+    /// Merges the hyperloglog `rhs` into `self` without checking that precisions and hashers
+    /// are the same on both terms.
+    ///
+    /// Other threads may keep inserting into both hyperloglogs meanwhile.
+    pub fn merge_from_unchecked(&self, rhs: &Self) {
+        for (register, rhs_register) in self.registers.iter().zip(rhs.registers.iter()) {
+            let value = rhs_register.load(Ordering::Relaxed);
+            if register.load(Ordering::Relaxed) < value {
+                register.fetch_max(value, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Copies the registers, as currently read, into a new register storage, or returns
+    /// `None` if the storage does not support the precision of the hyperloglog.
+    fn load_registers<R>(&self) -> Option<R>
+    where
+        R: RegisterStorage,
+    {
+        if !R::supports_precision(self.precision) {
+            return None;
+        }
+        let mut registers = R::with_precision(self.precision);
+        for (index, register) in self.registers.iter().enumerate() {
+            let value = register.load(Ordering::Relaxed);
+            if value > 0 {
+                registers.update(index, value);
+            }
+        }
+        Some(registers)
+    }
+}
+
+impl<T, S, E> ConcurrentHyperLogLog<T, S, E>
+where
+    T: ?Sized,
+    S: HasherIdentity,
+{
+    /// Merges the hyperloglog `rhs` into `self`.
+    ///
+    /// Other threads may keep inserting into both hyperloglogs meanwhile.
+    pub fn try_merge_from(&self, rhs: &Self) -> Result<(), TryMergeError> {
+        check_merge_params(
+            (self.precision, &self.hash_builder),
+            (rhs.precision, &rhs.hash_builder),
+        )?;
+        self.merge_from_unchecked(rhs);
+        Ok(())
+    }
+}
+
+impl<T, S, E> ConcurrentHyperLogLog<T, S, E>
+where
+    T: ?Sized,
+    S: Clone,
+    E: Clone,
+{
+    /// Returns a regular hyperloglog holding the registers as currently read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{ConcurrentHyperLogLog, HyperLogLog};
+    ///
+    /// let hll = ConcurrentHyperLogLog::<i32>::new();
+    /// hll.insert(&1);
+    /// let snapshot: HyperLogLog<i32> = hll.snapshot();
+    /// assert_eq!(snapshot.len(), 1);
+    /// ```
+    pub fn snapshot(&self) -> HyperLogLog<T, S, E> {
+        self.try_snapshot()
+            .expect("heap-allocated registers support all precisions")
+    }
+
+    /// Returns a regular hyperloglog holding the registers as currently read, in another
+    /// register storage, or `None` if the storage does not
+    /// [support](RegisterStorage::supports_precision) the precision of the hyperloglog.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{ConcurrentHyperLogLog, InlineHyperLogLog, Precision};
+    ///
+    /// let hll = ConcurrentHyperLogLog::<i32>::with_precision(Precision::P10);
+    /// hll.insert(&1);
+    /// let snapshot: InlineHyperLogLog<i32, 10> = hll.try_snapshot().unwrap();
+    /// assert_eq!(snapshot.len(), 1);
+    /// assert!(hll
+    ///     .try_snapshot::<hyperloglog::InlineRegisters<12>>()
+    ///     .is_none());
+    /// ```
+    pub fn try_snapshot<R>(&self) -> Option<HyperLogLog<T, S, E, R>>
+    where
+        R: RegisterStorage,
+    {
+        Some(HyperLogLog::from_parts(
+            self.load_registers()?,
+            self.hash_builder.clone(),
+            self.estimator.clone(),
+        ))
+    }
+}
+
+impl<T, S, E> ConcurrentHyperLogLog<T, S, E>
+where
+    T: ?Sized,
+    E: Estimator,
+{
+    /// Calculates the approximate number of different elements.
+    pub fn len(&self) -> usize {
+        self.estimate_with(&self.estimator).round() as usize
+    }
+
+    /// Estimates the number of different elements, together with its standard error and
+    /// the bounds of a confidence interval at the given confidence level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not strictly between 0 and 1.
+    pub fn estimate(&self, confidence: f64) -> Estimate {
+        Estimate::from_histogram(&self.estimator, &self.histogram(), confidence)
+    }
+}
+
+impl<T, S, E> ConcurrentHyperLogLog<T, S, E>
+where
+    T: ?Sized,
+    S: BuildHasher,
+{
+    /// Adds a value to the hyperloglog.
+    pub fn insert<Q>(&self, value: &Q)
+    where
+        T: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        let h = self.hash_builder.hash_one(value);
+        self.insert_hash(h);
+    }
+}
+
+impl<T, S, E> Debug for ConcurrentHyperLogLog<T, S, E>
+where
+    T: ?Sized,
+    S: Debug,
+    E: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConcurrentHyperLogLog")
+            .field("registers", &self.registers)
+            .field("precision", &self.precision)
+            .field("hash_builder", &self.hash_builder)
+            .field("estimator", &self.estimator)
+            .field("phantom", &self.phantom)
+            .finish()
+    }
+}
+
+impl<T, S, E> Default for ConcurrentHyperLogLog<T, S, E>
+where
+    T: ?Sized,
+    S: Default,
+    E: Default,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T, S, E> Extend<&'a T> for ConcurrentHyperLogLog<T, S, E>
+where
+    T: 'a + Hash + ?Sized,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<T, S, E> Extend<T> for ConcurrentHyperLogLog<T, S, E>
+where
+    T: Hash,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(&value);
+        }
+    }
+}
+
+impl<T, S, E, R> From<HyperLogLog<T, S, E, R>> for ConcurrentHyperLogLog<T, S, E>
+where
+    T: ?Sized,
+    R: RegisterStorage,
+{
+    /// Converts a hyperloglog into a concurrent one, dropping its HIP estimator if any.
+    fn from(hll: HyperLogLog<T, S, E, R>) -> Self {
+        let (registers, hash_builder, estimator) = hll.into_parts();
+        Self {
+            registers: registers.iter().map(AtomicU8::new).collect(),
+            precision: registers.precision(),
+            hash_builder,
+            estimator,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, S, E> From<ConcurrentHyperLogLog<T, S, E>> for HyperLogLog<T, S, E>
+where
+    T: ?Sized,
+{
+    fn from(hll: ConcurrentHyperLogLog<T, S, E>) -> Self {
+        let registers = hll
+            .load_registers()
+            .expect("heap-allocated registers support all precisions");
+        HyperLogLog::from_parts(registers, hll.hash_builder, hll.estimator)
+    }
+}
@@ -1,4 +1,4 @@
-use std::{
+use core::{
     error::Error,
     fmt::{self, Display},
 };
@@ -15,6 +15,29 @@ impl Display for TryFromIntError {
 
 impl Error for TryFromIntError {}
 
+/// Error type returned when a byte slice does not hold valid registers for a precision.
+///
+/// The slice must hold exactly `2^p` registers, each at most `65 - p`.
+///
+/// # Examples
+///
+/// ```
+/// use hyperloglog::{HyperLogLogRef, Precision};
+///
+/// let bytes = [0; 100];
+/// assert!(HyperLogLogRef::<i32>::new(&bytes, Precision::P8).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TryFromSliceError(pub(crate) ());
+
+impl Display for TryFromSliceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "invalid registers for precision".fmt(f)
+    }
+}
+
+impl Error for TryFromSliceError {}
+
 /// A list specifying categories of merging error.
 ///
 /// It is used with the [`TryMergeError`] type.
@@ -64,3 +87,54 @@ impl Display for TryMergeError {
 }
 
 impl Error for TryMergeError {}
+
+/// A list specifying categories of deserialization error.
+///
+/// It is used with the [`FromBytesError`] type.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FromBytesErrorKind {
+    /// Bytes do not hold a serialized hyperloglog.
+    Invalid,
+    /// The hyperloglog was serialized with another hasher.
+    Hasher,
+    /// The register storage does not support the precision of the serialized hyperloglog.
+    Precision,
+}
+
+/// Error type returned when deserializing a hyperloglog fails.
+///
+/// # Examples
+///
+/// ```
+/// use hyperloglog::{BuildXxHash64, FromBytesErrorKind, HyperLogLog};
+///
+/// let error = HyperLogLog::<i32>::from_bytes(b"invalid", BuildXxHash64::default()).unwrap_err();
+/// assert_eq!(error.kind(), FromBytesErrorKind::Invalid);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FromBytesError {
+    pub(crate) kind: FromBytesErrorKind,
+}
+
+impl FromBytesError {
+    /// Returns the corresponding [`FromBytesErrorKind`] for this error.
+    #[inline]
+    pub fn kind(&self) -> FromBytesErrorKind {
+        self.kind
+    }
+}
+
+impl Display for FromBytesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(
+            match self.kind() {
+                FromBytesErrorKind::Invalid => "invalid serialized hyperloglog",
+                FromBytesErrorKind::Hasher => "incompatible hasher",
+                FromBytesErrorKind::Precision => "unsupported precision",
+            },
+            f,
+        )
+    }
+}
+
+impl Error for FromBytesError {}
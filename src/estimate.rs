@@ -0,0 +1,201 @@
+use core::fmt::{self, Display};
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
+use crate::{estimator::Estimator, histogram::Histogram};
+
+/// An estimated number of distinct elements, together with its uncertainty.
+///
+/// It is returned by [`HyperLogLog::estimate`](crate::HyperLogLog::estimate), by
+/// [`HyperLogLog::try_intersection`](crate::HyperLogLog::try_intersection), and by the
+/// methods of [`JointEstimate`](crate::JointEstimate).
+///
+/// # Examples
+///
+/// ```
+/// use hyperloglog::HyperLogLog;
+///
+/// let mut hll = HyperLogLog::<i32>::new();
+/// for i in 0..100_000 {
+///     hll.insert(&i);
+/// }
+/// let estimate = hll.estimate(0.95);
+/// assert!(estimate.lower_bound() < estimate.value());
+/// assert!(estimate.value() < estimate.upper_bound());
+/// println!("{estimate}");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    value: f64,
+    standard_error: f64,
+    relative_standard_error: f64,
+    confidence: f64,
+    margin: f64,
+}
+
+impl Estimate {
+    /// Creates an estimate from a point estimate, its relative standard error,
+    /// and a confidence level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not strictly between 0 and 1.
+    pub(crate) fn new(value: f64, relative_standard_error: f64, confidence: f64) -> Self {
+        Self::with_errors(
+            value,
+            relative_standard_error * value,
+            relative_standard_error,
+            confidence,
+        )
+    }
+
+    /// Creates an estimate from the histogram of register values, with the value and the
+    /// relative standard error given by an estimator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not strictly between 0 and 1.
+    pub(crate) fn from_histogram<E>(estimator: &E, histogram: &Histogram, confidence: f64) -> Self
+    where
+        E: Estimator + ?Sized,
+    {
+        Self::new(
+            estimator.estimate(histogram),
+            estimator.relative_standard_error(histogram),
+            confidence,
+        )
+    }
+
+    /// Creates an estimate from a point estimate, its standard error, and a confidence
+    /// level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not strictly between 0 and 1.
+    pub(crate) fn with_standard_error(value: f64, standard_error: f64, confidence: f64) -> Self {
+        let relative_standard_error = if standard_error == 0. {
+            0.
+        } else {
+            standard_error / value
+        };
+        Self::with_errors(value, standard_error, relative_standard_error, confidence)
+    }
+
+    fn with_errors(
+        value: f64,
+        standard_error: f64,
+        relative_standard_error: f64,
+        confidence: f64,
+    ) -> Self {
+        assert!(
+            0. < confidence && confidence < 1.,
+            "confidence must be between 0 and 1"
+        );
+        let z = normal_quantile(0.5 + confidence / 2.);
+        Self {
+            value,
+            standard_error,
+            relative_standard_error,
+            confidence,
+            margin: z * standard_error,
+        }
+    }
+
+    /// Returns the point estimate.
+    #[inline]
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Returns the standard error of the estimate.
+    #[inline]
+    pub fn standard_error(&self) -> f64 {
+        self.standard_error
+    }
+
+    /// Returns the standard error of the estimate, relative to its value.
+    #[inline]
+    pub fn relative_standard_error(&self) -> f64 {
+        self.relative_standard_error
+    }
+
+    /// Returns the confidence level of the bounds.
+    #[inline]
+    pub fn confidence(&self) -> f64 {
+        self.confidence
+    }
+
+    /// Returns the lower bound of the confidence interval.
+    #[inline]
+    pub fn lower_bound(&self) -> f64 {
+        (self.value - self.margin).max(0.)
+    }
+
+    /// Returns the upper bound of the confidence interval.
+    #[inline]
+    pub fn upper_bound(&self) -> f64 {
+        self.value + self.margin
+    }
+}
+
+impl Display for Estimate {
+    /// Formats the estimate as its value and its relative margin of error,
+    /// such as `12400 ± 2.0%`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let relative_margin = if self.value > 0. {
+            self.margin / self.value
+        } else {
+            0.
+        };
+        write!(f, "{:.0} ± {:.1}%", self.value, 100. * relative_margin)
+    }
+}
+
+/// Computes the quantile function of the standard normal distribution, for `0 < p < 1`.
+///
+/// This is Peter Acklam's rational approximation, with a relative error below `1.15e-9`.
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1,
+        2.209460984245205e2,
+        -2.759285104469687e2,
+        1.38357751867269e2,
+        -3.066479806614716e1,
+        2.506628277459239,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1,
+        1.615858368580409e2,
+        -1.556989798598866e2,
+        6.680131188771972e1,
+        -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3,
+        -3.223964580411365e-1,
+        -2.400758277161838,
+        -2.549732539343734,
+        4.374664141464968,
+        2.938163982698783,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3,
+        3.224671290700398e-1,
+        2.445134137142996,
+        3.754408661907416,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    if p < P_LOW {
+        let q = (-2. * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.)
+    } else if p <= 1. - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.)
+    } else {
+        -normal_quantile(1. - p)
+    }
+}
@@ -0,0 +1,460 @@
+use core::f64::consts::LN_2;
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
+use crate::{bias, histogram::Histogram, precision::Precision};
+
+/// A formula to estimate the number of distinct elements from the registers of a hyperloglog.
+///
+/// Estimators only see the [`Histogram`] of register values, so the same sketch can be
+/// evaluated with several estimators through [`HyperLogLog::estimate_with`].
+///
+/// # Examples
+///
+/// ```
+/// use hyperloglog::{Estimator, Histogram, HyperLogLog};
+///
+/// /// Plain linear counting, only accurate for small cardinalities.
+/// struct LinearCounting;
+///
+/// impl Estimator for LinearCounting {
+///     fn estimate(&self, histogram: &Histogram) -> f64 {
+///         let m = histogram.num_registers() as f64;
+///         m * (m / histogram.count(0) as f64).ln()
+///     }
+/// }
+///
+/// let mut hll = HyperLogLog::<i32>::new().with_estimator(LinearCounting);
+/// for i in 0..100 {
+///     hll.insert(&i);
+/// }
+/// assert!((50..150).contains(&hll.len()));
+/// ```
+///
+/// [`HyperLogLog::estimate_with`]: crate::HyperLogLog::estimate_with
+pub trait Estimator {
+    /// Estimates the number of distinct elements from the histogram of register values.
+    fn estimate(&self, histogram: &Histogram) -> f64;
+
+    /// Returns the standard error of the estimate from the histogram, relative to its
+    /// value.
+    ///
+    /// Defaults to the asymptotic error of HyperLogLog, `sqrt(3 * ln(2) - 1) / sqrt(m)`,
+    /// that is, about `1.04 / sqrt(m)` where `m` is the number of registers.
+    fn relative_standard_error(&self, histogram: &Histogram) -> f64 {
+        asymptotic_relative_standard_error(histogram.precision())
+    }
+}
+
+impl<E> Estimator for &E
+where
+    E: Estimator + ?Sized,
+{
+    #[inline]
+    fn estimate(&self, histogram: &Histogram) -> f64 {
+        (**self).estimate(histogram)
+    }
+
+    #[inline]
+    fn relative_standard_error(&self, histogram: &Histogram) -> f64 {
+        (**self).relative_standard_error(histogram)
+    }
+}
+
+/// The original HyperLogLog estimator.
+///
+/// It computes the raw `alpha`-based estimate, and switches to linear counting below
+/// `2.5 * m`, `m` being the number of registers.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Classic;
+
+impl Classic {
+    /// Returns `true` if linear counting is used for the histogram.
+    fn is_linear_counting(histogram: &Histogram) -> bool {
+        let m = histogram.num_registers() as f64;
+        raw_estimate(histogram) < 2.5 * m && histogram.count(0) != 0
+    }
+}
+
+impl Estimator for Classic {
+    fn estimate(&self, histogram: &Histogram) -> f64 {
+        if Self::is_linear_counting(histogram) {
+            // Small range correction.
+            linear_counting(histogram)
+        } else {
+            // We're using 64-bit hashes, so large range correction is not needed.
+            raw_estimate(histogram)
+        }
+    }
+
+    /// Returns the error of linear counting in the small range, and the asymptotic error
+    /// otherwise.
+    fn relative_standard_error(&self, histogram: &Histogram) -> f64 {
+        if Self::is_linear_counting(histogram) {
+            linear_counting_relative_standard_error(histogram)
+        } else {
+            asymptotic_relative_standard_error(histogram.precision())
+        }
+    }
+}
+
+/// The HyperLogLog++ estimator.
+///
+/// It corrects the raw `alpha`-based estimate with empirical biases, and switches to
+/// linear counting for small cardinalities.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct HyperLogLogPlusPlus;
+
+impl HyperLogLogPlusPlus {
+    /// Returns `true` if linear counting is used for the histogram.
+    fn is_linear_counting(histogram: &Histogram) -> bool {
+        histogram.count(0) != 0
+            && linear_counting(histogram) <= bias::threshold(histogram.precision())
+    }
+}
+
+impl Estimator for HyperLogLogPlusPlus {
+    fn estimate(&self, histogram: &Histogram) -> f64 {
+        if Self::is_linear_counting(histogram) {
+            // Small range correction.
+            return linear_counting(histogram);
+        }
+        let precision = histogram.precision();
+        let m = histogram.num_registers() as f64;
+        let mut e = raw_estimate(histogram);
+        if e <= 5. * m {
+            // Empirical bias correction from HyperLogLog++.
+            e -= bias::estimate_bias(precision, e);
+        }
+        // We're using 64-bit hashes, so large range correction is not needed.
+        e.max(0.)
+    }
+
+    /// Returns the error of linear counting in the small range, and the asymptotic error
+    /// otherwise.
+    fn relative_standard_error(&self, histogram: &Histogram) -> f64 {
+        if Self::is_linear_counting(histogram) {
+            linear_counting_relative_standard_error(histogram)
+        } else {
+            asymptotic_relative_standard_error(histogram.precision())
+        }
+    }
+}
+
+/// Otmar Ertl's improved raw estimator.
+///
+/// It needs neither empirical data nor range switches, and remains accurate over the
+/// whole cardinality range, including at small precisions.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Improved;
+
+impl Estimator for Improved {
+    fn estimate(&self, histogram: &Histogram) -> f64 {
+        let m = histogram.num_registers() as f64;
+        let counts = histogram.counts();
+        // Register values range from 0 to q + 1.
+        let q = counts.len() - 2;
+        let mut z = m * tau(1. - (counts[q + 1] as f64) / m);
+        for count in counts[1..=q].iter().rev() {
+            z = 0.5 * (z + (*count as f64));
+        }
+        z += m * sigma((counts[0] as f64) / m);
+        m * m / (2. * LN_2 * z)
+    }
+
+    /// Returns the asymptotic error, which Otmar Ertl shows holds over the whole range,
+    /// except for small cardinalities where the estimator is as accurate as linear
+    /// counting.
+    fn relative_standard_error(&self, histogram: &Histogram) -> f64 {
+        small_range_relative_standard_error(histogram)
+    }
+}
+
+/// Otmar Ertl's maximum-likelihood estimator.
+///
+/// It has the lowest variance of the built-in estimators, but is computed iteratively and
+/// is therefore the slowest one.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct MaximumLikelihood;
+
+impl MaximumLikelihood {
+    /// Relative tolerance at which the iteration stops.
+    const TOLERANCE: f64 = 1e-10;
+
+    /// Upper bound on the number of iterations.
+    const MAX_ITERATIONS: usize = 100;
+
+    /// Estimates the number of distinct elements, also reporting the number of iterations
+    /// it took to converge.
+    ///
+    /// With `x = n / m` and `phi(t) = t / (e^t - 1)`, the estimate is the root of
+    /// `g(x) = x * a - sum(C_k * phi(x / 2^min(k, q)), k >= 1)`, where `C_k` is the number
+    /// of registers with value `k` and `a = C_0 + sum(C_k / 2^k, 1 <= k <= q)`.
+    /// It is found with Newton's method.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{HyperLogLog, MaximumLikelihood};
+    ///
+    /// let mut hll = HyperLogLog::<i32>::new();
+    /// for i in 0..100_000 {
+    ///     hll.insert(&i);
+    /// }
+    /// let estimate = MaximumLikelihood.estimate_with_iterations(&hll.histogram());
+    /// assert!((50_000.0..150_000.0).contains(&estimate.estimate()));
+    /// ```
+    pub fn estimate_with_iterations(&self, histogram: &Histogram) -> MaximumLikelihoodEstimate {
+        let m = histogram.num_registers() as f64;
+        let counts = histogram.counts();
+        let q = counts.len() - 2;
+        if counts[0] as f64 == m {
+            return MaximumLikelihoodEstimate {
+                estimate: 0.,
+                iterations: 0,
+            };
+        }
+        let a = (counts[0] as f64)
+            + (1..=q)
+                .map(|k| (counts[k] as f64) * 2f64.powi(-(k as i32)))
+                .sum::<f64>();
+        if a == 0. {
+            // All registers are saturated.
+            return MaximumLikelihoodEstimate {
+                estimate: f64::INFINITY,
+                iterations: 0,
+            };
+        }
+        let terms = || {
+            (1..=q + 1)
+                .filter(|k| counts[*k] != 0)
+                .map(|k| (counts[k] as f64, 2f64.powi(-(k.min(q) as i32))))
+        };
+        // g is increasing and concave, and g(0) < 0, so Newton's method started from 0
+        // increases monotonically towards the root.
+        let mut x = 0.;
+        let mut iterations = 0;
+        while iterations < Self::MAX_ITERATIONS {
+            iterations += 1;
+            let (mut g, mut dg) = (x * a, a);
+            for (count, scale) in terms() {
+                let t = x * scale;
+                g -= count * phi(t);
+                dg -= count * scale * phi_derivative(t);
+            }
+            if g >= 0. {
+                break;
+            }
+            let dx = -g / dg;
+            x += dx;
+            if dx <= x * Self::TOLERANCE {
+                break;
+            }
+        }
+        MaximumLikelihoodEstimate {
+            estimate: m * x,
+            iterations,
+        }
+    }
+}
+
+impl Estimator for MaximumLikelihood {
+    #[inline]
+    fn estimate(&self, histogram: &Histogram) -> f64 {
+        self.estimate_with_iterations(histogram).estimate()
+    }
+
+    /// Returns the asymptotic error, which is also the Cramér–Rao bound of estimators
+    /// based on the registers, except for small cardinalities where the estimator is as
+    /// accurate as linear counting.
+    fn relative_standard_error(&self, histogram: &Histogram) -> f64 {
+        small_range_relative_standard_error(histogram)
+    }
+}
+
+/// The result of a maximum-likelihood estimation.
+///
+/// It is returned by [`MaximumLikelihood::estimate_with_iterations`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MaximumLikelihoodEstimate {
+    estimate: f64,
+    iterations: usize,
+}
+
+impl MaximumLikelihoodEstimate {
+    /// Returns the estimated number of distinct elements.
+    #[inline]
+    pub fn estimate(&self) -> f64 {
+        self.estimate
+    }
+
+    /// Returns the number of iterations it took to converge.
+    #[inline]
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+}
+
+/// A built-in estimator, selected at runtime.
+///
+/// This is the default estimator of [`HyperLogLog`](crate::HyperLogLog), so that the
+/// estimator of a sketch can be changed without changing its type.
+///
+/// # Examples
+///
+/// ```
+/// use hyperloglog::{EstimationStrategy, HyperLogLog};
+///
+/// let mut hll = HyperLogLog::<i32>::new();
+/// hll.set_estimator(EstimationStrategy::Improved);
+/// for i in 0..100_000 {
+///     hll.insert(&i);
+/// }
+/// assert!((50_000..150_000).contains(&hll.len()));
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum EstimationStrategy {
+    /// The [`Classic`] estimator.
+    Classic,
+    /// The [`HyperLogLogPlusPlus`] estimator (default).
+    #[default]
+    HyperLogLogPlusPlus,
+    /// The [`Improved`] estimator.
+    Improved,
+    /// The [`MaximumLikelihood`] estimator.
+    MaximumLikelihood,
+}
+
+impl Estimator for EstimationStrategy {
+    fn estimate(&self, histogram: &Histogram) -> f64 {
+        match self {
+            Self::Classic => Classic.estimate(histogram),
+            Self::HyperLogLogPlusPlus => HyperLogLogPlusPlus.estimate(histogram),
+            Self::Improved => Improved.estimate(histogram),
+            Self::MaximumLikelihood => MaximumLikelihood.estimate(histogram),
+        }
+    }
+
+    fn relative_standard_error(&self, histogram: &Histogram) -> f64 {
+        match self {
+            Self::Classic => Classic.relative_standard_error(histogram),
+            Self::HyperLogLogPlusPlus => HyperLogLogPlusPlus.relative_standard_error(histogram),
+            Self::Improved => Improved.relative_standard_error(histogram),
+            Self::MaximumLikelihood => MaximumLikelihood.relative_standard_error(histogram),
+        }
+    }
+}
+
+fn alpha(num_bits: u8, num_registers: usize) -> f64 {
+    if num_bits == 4 {
+        0.673
+    } else if num_bits == 5 {
+        0.697
+    } else if num_bits == 6 {
+        0.709
+    } else {
+        0.7213 / (1. + 1.079 / (num_registers as f64))
+    }
+}
+
+/// Computes the linear counting estimate, `m * ln(m / V)` where `V` is the number of
+/// zero registers.
+fn linear_counting(histogram: &Histogram) -> f64 {
+    let m = histogram.num_registers() as f64;
+    m * (m / (histogram.count(0) as f64)).ln()
+}
+
+/// Returns the asymptotic relative standard error of HyperLogLog,
+/// `sqrt(3 * ln(2) - 1) / sqrt(m)`.
+fn asymptotic_relative_standard_error(precision: Precision) -> f64 {
+    (3. * LN_2 - 1.).sqrt() / ((1u64 << precision.get()) as f64).sqrt()
+}
+
+/// Returns the relative standard error of linear counting, `sqrt(m * (e^t - t - 1)) / n`
+/// with `t = n / m`, as given by Whang et al., or infinity if no register is zero.
+fn linear_counting_relative_standard_error(histogram: &Histogram) -> f64 {
+    let m = histogram.num_registers() as f64;
+    let zeros = histogram.count(0);
+    if zeros == 0 {
+        return f64::INFINITY;
+    }
+    let t = (m / (zeros as f64)).ln();
+    if t == 0. {
+        // The limit for small cardinalities.
+        return (0.5 / m).sqrt();
+    }
+    ((t.exp_m1() - t) / m).sqrt() / t
+}
+
+/// Returns the relative standard error of estimators that are as accurate as linear
+/// counting for small cardinalities, and reach the asymptotic error otherwise.
+fn small_range_relative_standard_error(histogram: &Histogram) -> f64 {
+    linear_counting_relative_standard_error(histogram)
+        .min(asymptotic_relative_standard_error(histogram.precision()))
+}
+
+/// Computes the raw `alpha`-based estimate.
+fn raw_estimate(histogram: &Histogram) -> f64 {
+    let num_registers = histogram.num_registers();
+    let m = num_registers as f64;
+    alpha(histogram.precision().get(), num_registers) * m * m / histogram.harmonic_sum()
+}
+
+/// Computes `x + sum(x^(2^k) * 2^(k-1), k >= 1)`.
+fn sigma(mut x: f64) -> f64 {
+    if x == 1. {
+        return f64::INFINITY;
+    }
+    let mut y = 1.;
+    let mut z = x;
+    loop {
+        x *= x;
+        let previous_z = z;
+        z += x * y;
+        y += y;
+        if z == previous_z {
+            return z;
+        }
+    }
+}
+
+/// Computes `(1 - x - sum((1 - x^(2^-k))^2 * 2^-k, k >= 1)) / 3`.
+fn tau(mut x: f64) -> f64 {
+    if x == 0. || x == 1. {
+        return 0.;
+    }
+    let mut y = 1.;
+    let mut z = 1. - x;
+    loop {
+        x = x.sqrt();
+        let previous_z = z;
+        y *= 0.5;
+        z -= (1. - x).powi(2) * y;
+        if z == previous_z {
+            return z / 3.;
+        }
+    }
+}
+
+/// Computes `t / (e^t - 1)`.
+fn phi(t: f64) -> f64 {
+    if t == 0. {
+        1.
+    } else {
+        t / t.exp_m1()
+    }
+}
+
+/// Computes the derivative of [`phi`].
+fn phi_derivative(t: f64) -> f64 {
+    if t < 1e-3 {
+        // Taylor expansion, avoiding the cancellation of the closed form.
+        -0.5 + t / 6.
+    } else if t > 700. {
+        0.
+    } else {
+        let e = t.exp_m1();
+        (e - t * (e + 1.)) / (e * e)
+    }
+}
@@ -0,0 +1,46 @@
+use core::f64::consts::LN_2;
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
+use crate::{histogram::Histogram, precision::Precision};
+
+/// State of the historic inverse probability (HIP) estimator.
+///
+/// Each time a register changes, the estimate is increased by the inverse of the
+/// probability that a new distinct element changes a register, which is
+/// `sum(2^-r_j) / m`. That harmonic sum is kept up to date on each change, so that
+/// updates do not depend on the number of registers, whatever their storage.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hip {
+    estimate: f64,
+    harmonic_sum: f64,
+}
+
+impl Hip {
+    /// Starts the estimator from the current histogram of the registers, assuming
+    /// `estimate` distinct elements were already added.
+    #[inline]
+    pub fn new(histogram: &Histogram, estimate: f64) -> Self {
+        Self {
+            estimate,
+            harmonic_sum: histogram.harmonic_sum(),
+        }
+    }
+
+    /// Returns the relative standard error of the estimator, `sqrt(ln(2)) / sqrt(m)`.
+    pub fn relative_standard_error(precision: Precision) -> f64 {
+        LN_2.sqrt() / ((1u64 << precision.get()) as f64).sqrt()
+    }
+
+    #[inline]
+    pub fn estimate(&self) -> f64 {
+        self.estimate
+    }
+
+    /// Accounts for a register raised from `previous` to `value`.
+    #[inline]
+    pub fn update(&mut self, num_registers: usize, previous: u8, value: u8) {
+        self.estimate += (num_registers as f64) / self.harmonic_sum;
+        self.harmonic_sum += 2f64.powi(-(value as i32)) - 2f64.powi(-(previous as i32));
+    }
+}
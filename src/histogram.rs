@@ -0,0 +1,97 @@
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
+use crate::precision::Precision;
+
+/// Number of possible register values.
+///
+/// With 64-bit hashes and at least 4 bits of precision, register values never exceed 61.
+pub(crate) const NUM_VALUES: usize = 64;
+
+/// The number of registers holding each value, in a hyperloglog.
+///
+/// Estimators only depend on the histogram of register values, not on their order.
+///
+/// # Examples
+///
+/// ```
+/// use hyperloglog::HyperLogLog;
+///
+/// let mut hll = HyperLogLog::<i32>::new();
+/// hll.insert(&1);
+/// let histogram = hll.histogram();
+/// assert_eq!(histogram.count(0), 4095);
+/// assert_eq!(histogram.counts().iter().sum::<u32>(), 4096);
+/// ```
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Histogram {
+    precision: Precision,
+    counts: [u32; NUM_VALUES],
+}
+
+impl Histogram {
+    #[inline]
+    pub(crate) fn new(precision: Precision, counts: [u32; NUM_VALUES]) -> Self {
+        Self { precision, counts }
+    }
+
+    /// Counts register values.
+    pub(crate) fn from_values<I>(precision: Precision, values: I) -> Self
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        let mut counts = [0; NUM_VALUES];
+        for value in values {
+            counts[value as usize] += 1;
+        }
+        Self::new(precision, counts)
+    }
+
+    /// Returns the precision of the hyperloglog.
+    #[inline]
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    /// Returns the number of registers, that is, `2^p`.
+    #[inline]
+    pub fn num_registers(&self) -> usize {
+        1 << self.precision.get()
+    }
+
+    /// Returns the largest possible register value, that is, `65 - p`.
+    #[inline]
+    pub fn max_value(&self) -> u8 {
+        65 - self.precision.get()
+    }
+
+    /// Returns the number of registers holding each value, from 0 to
+    /// [`max_value`](Histogram::max_value) inclusive.
+    #[inline]
+    pub fn counts(&self) -> &[u32] {
+        &self.counts[..=self.max_value() as usize]
+    }
+
+    /// Returns the number of registers holding the given value.
+    #[inline]
+    pub fn count(&self, value: u8) -> u32 {
+        self.counts.get(value as usize).copied().unwrap_or(0)
+    }
+
+    /// Returns the sum of `2^-r` over all register values `r`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let hll = HyperLogLog::<i32>::new();
+    /// assert_eq!(hll.histogram().harmonic_sum(), 4096.0);
+    /// ```
+    pub fn harmonic_sum(&self) -> f64 {
+        self.counts()
+            .iter()
+            .enumerate()
+            .map(|(value, count)| (*count as f64) * 2f64.powi(-(value as i32)))
+            .sum()
+    }
+}
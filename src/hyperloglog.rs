@@ -1,31 +1,79 @@
-use std::{
+use core::{
     borrow::Borrow,
-    collections::hash_map::{DefaultHasher, RandomState},
     fmt::{self, Debug},
-    hash::{BuildHasher, BuildHasherDefault, Hash, Hasher},
+    hash::{BuildHasher, Hash},
     marker::PhantomData,
 };
 
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
 use crate::{
     error::{TryMergeError, TryMergeErrorKind},
+    estimate::Estimate,
+    estimator::{Estimator, MaximumLikelihood, MaximumLikelihoodEstimate},
+    hip::Hip,
+    histogram::{Histogram, NUM_VALUES},
+    identity::HasherIdentity,
     precision::Precision,
-    registers::Registers,
+    simd,
+    storage::RegisterStorage,
+    xxhash::{hash_bytes, BuildXxHash64},
+};
+#[cfg(feature = "alloc")]
+use crate::{
+    estimator::EstimationStrategy,
+    registers::{RegisterLayout, Registers},
 };
 
-fn alpha(num_bits: u8, num_registers: usize) -> f64 {
-    if num_bits == 4 {
-        0.673
-    } else if num_bits == 5 {
-        0.697
-    } else if num_bits == 6 {
-        0.709
-    } else {
-        0.7213 / (1. + 1.079 / (num_registers as f64))
-    }
+/// Hasher builder used by default, whose algorithm is stable across platforms and
+/// releases.
+pub(crate) type DefaultBuildHasher = BuildXxHash64;
+
+/// Declares the hyperloglog type, whose default type parameters are only available with
+/// the `alloc` feature, as there is no default register storage without it. Enabling the
+/// feature then only adds defaults, and never changes what a type refers to.
+macro_rules! hyperloglog_type {
+    ($(#[$attr:meta])* $vis:vis struct $name:ident { $($fields:tt)* }) => {
+        $(#[$attr])*
+        #[cfg(feature = "alloc")]
+        $vis struct $name<T, S = DefaultBuildHasher, E = EstimationStrategy, R = Registers>
+        where
+            T: ?Sized,
+        {
+            $($fields)*
+        }
+
+        $(#[$attr])*
+        #[cfg(not(feature = "alloc"))]
+        $vis struct $name<T, S, E, R>
+        where
+            T: ?Sized,
+        {
+            $($fields)*
+        }
+    };
 }
 
+hyperloglog_type! {
 /// A hyperloglog data structure to estimate the number of distinct elements in a data stream.
 ///
+/// Registers start in a sparse representation that only stores the non-zero registers,
+/// and switch to a dense array of `2^p` registers once that becomes more compact.
+/// Dense registers take one byte each by default, or less with another
+/// [layout](HyperLogLog::with_layout): six bits with [`Packed`](RegisterLayout::Packed),
+/// and four bits with [`Compact`](RegisterLayout::Compact).
+/// Other register storages may be used through the `R` type parameter, see
+/// [`RegisterStorage`]. Without the `alloc` feature, the type parameters have no defaults,
+/// and registers are usually stored inline, as in
+/// [`InlineHyperLogLog`](crate::InlineHyperLogLog).
+///
+/// Values are hashed with [`XxHash64`](crate::XxHash64) by default, whose algorithm is
+/// frozen. Hyperloglogs filled with fixed-width integers, or through
+/// [`insert_bytes`](HyperLogLog::insert_bytes) or [`insert_hash`](HyperLogLog::insert_hash),
+/// may therefore be merged across platforms and Rust releases. Other values are hashed
+/// through their [`Hash`] implementation, whose output the standard library does not
+/// guarantee to be stable. Another hasher may be used through the `S` type parameter.
+///
 /// # Examples
 ///
 /// ```
@@ -37,20 +85,34 @@ fn alpha(num_bits: u8, num_registers: usize) -> f64 {
 /// }
 /// assert!((50_000..150_000).contains(&hll.len()));
 /// ```
-pub struct HyperLogLog<T, S = BuildHasherDefault<DefaultHasher>>
-where
-    T: ?Sized,
-{
-    alpha: f64,
-    registers: Registers,
+///
+/// The estimator used by [`len`](HyperLogLog::len) is a type parameter, which defaults to
+/// [`EstimationStrategy`] so that it may also be chosen at runtime:
+///
+/// ```
+/// use hyperloglog::{HyperLogLog, Improved};
+///
+/// let mut hll = HyperLogLog::<i32>::new().with_estimator(Improved);
+/// for i in 0..100_000 {
+///     hll.insert(&i);
+/// }
+/// assert!((50_000..150_000).contains(&hll.len()));
+/// ```
+pub struct HyperLogLog {
+    registers: R,
     hash_builder: S,
+    estimator: E,
+    hip: Option<Hip>,
     phantom: PhantomData<T>,
 }
+}
 
-impl<T, S> HyperLogLog<T, S>
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
 where
     T: ?Sized,
+    R: RegisterStorage,
     S: Default,
+    E: Default,
 {
     /// Creates a new empty hyperloglog with the default precision.
     ///
@@ -63,7 +125,10 @@ where
     /// ```
     #[inline]
     #[must_use]
-    pub fn new() -> Self {
+    pub fn new() -> Self
+    where
+        R: Default,
+    {
         Self::default()
     }
 
@@ -83,12 +148,14 @@ where
     }
 }
 
-impl<T, S> HyperLogLog<T, S>
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
 where
     T: ?Sized,
+    R: RegisterStorage,
+    E: Default,
 {
-    /// Creates a new empty hyperloglog with the default precision and the given hasher
-    /// to hash keys.
+    /// Creates a new empty hyperloglog with the given hasher to hash keys, and the
+    /// [default precision](RegisterStorage::default_precision) of its register storage.
     ///
     /// # Examples
     ///
@@ -104,7 +171,7 @@ where
     #[inline]
     #[must_use]
     pub fn with_hasher(hasher: S) -> Self {
-        Self::with_precision_and_hasher(Precision::default(), hasher)
+        Self::with_precision_and_hasher(R::default_precision(), hasher)
     }
 
     /// Creates a new empty hyperloglog with the given precision and the given hasher to hash keys.
@@ -123,19 +190,37 @@ where
     #[inline]
     #[must_use]
     pub fn with_precision_and_hasher(precision: Precision, hasher: S) -> Self {
-        Self::with_registers_and_hasher(Registers::with_precision(precision), hasher)
+        Self::with_registers_and_hasher(R::with_precision(precision), hasher)
     }
 
     #[inline]
-    fn with_registers_and_hasher(registers: Registers, hasher: S) -> Self {
+    fn with_registers_and_hasher(registers: R, hasher: S) -> Self {
+        Self::from_parts(registers, hasher, E::default())
+    }
+}
+
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
+where
+    T: ?Sized,
+    R: RegisterStorage,
+{
+    #[inline]
+    pub(crate) fn from_parts(registers: R, hasher: S, estimator: E) -> Self {
         Self {
-            alpha: alpha(registers.precision().get(), registers.len()),
             registers,
             hash_builder: hasher,
+            estimator,
+            hip: None,
             phantom: PhantomData,
         }
     }
 
+    #[cfg(all(feature = "alloc", target_has_atomic = "8"))]
+    #[inline]
+    pub(crate) fn into_parts(self) -> (R, S, E) {
+        (self.registers, self.hash_builder, self.estimator)
+    }
+
     /// Returns the precision of the hyperloglog.
     ///
     /// # Examples
@@ -169,6 +254,75 @@ where
         &self.hash_builder
     }
 
+    /// Returns a reference to the hyperloglog's [`Estimator`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{EstimationStrategy, HyperLogLog};
+    ///
+    /// let hll = HyperLogLog::<i32>::new();
+    /// assert_eq!(hll.estimator(), &EstimationStrategy::HyperLogLogPlusPlus);
+    /// ```
+    #[inline]
+    pub fn estimator(&self) -> &E {
+        &self.estimator
+    }
+
+    /// Sets the estimator used by [`len`](HyperLogLog::len).
+    ///
+    /// The estimator only affects estimation, so it may be changed at any time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{EstimationStrategy, HyperLogLog};
+    ///
+    /// let mut hll = HyperLogLog::<i32>::new();
+    /// hll.set_estimator(EstimationStrategy::Improved);
+    /// assert_eq!(hll.estimator(), &EstimationStrategy::Improved);
+    /// ```
+    #[inline]
+    pub fn set_estimator(&mut self, estimator: E) {
+        self.estimator = estimator;
+    }
+
+    /// Replaces the estimator of the hyperloglog, possibly changing its type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{HyperLogLog, MaximumLikelihood};
+    ///
+    /// let hll = HyperLogLog::<i32>::new().with_estimator(MaximumLikelihood);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_estimator<F>(self, estimator: F) -> HyperLogLog<T, S, F, R> {
+        let mut hll = HyperLogLog::from_parts(self.registers, self.hash_builder, estimator);
+        hll.hip = self.hip;
+        hll
+    }
+
+    /// Returns `true` if the number of elements is estimated with the historic inverse
+    /// probability (HIP) estimator.
+    ///
+    /// See [`with_hip`](HyperLogLog::with_hip) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let hll = HyperLogLog::<i32>::new();
+    /// assert!(!hll.is_hip_active());
+    /// assert!(hll.with_hip().is_hip_active());
+    /// ```
+    #[inline]
+    pub fn is_hip_active(&self) -> bool {
+        self.hip.is_some()
+    }
+
     /// Adds a hash value to the hyperloglog.
     ///
     /// This may be handy when the hash is previously computed, to avoid computing twice.
@@ -194,46 +348,144 @@ where
     /// hll.insert_hash(h);
     /// ```
     pub fn insert_hash(&mut self, h: u64) {
-        let num_bits = self.precision().get();
-        // Split h into (w, j) where w represents the (64 - p) upper bits, and j the p lower bits.
-        let w = h >> num_bits;
-        let j = h - (w << num_bits);
-        // Leftmost bit (1-based count).
-        let rho = (w.leading_zeros() + 1 - (num_bits as u32)) as u8;
-        self.registers.update(j as usize, rho);
+        let (j, rho) = split_hash(self.precision(), h);
+        let previous = self.registers.update(j, rho);
+        if let Some(hip) = &mut self.hip {
+            if previous < rho {
+                hip.update(self.registers.len(), previous, rho);
+            }
+        }
     }
 
-    /// Calculates the approximate number of different elements.
+    /// Adds raw bytes to the hyperloglog, regardless of its hasher.
+    ///
+    /// Bytes are hashed with [xxHash64](crate::XxHash64) and a zero seed, with no length
+    /// prefix nor suffix, then inserted with [`HyperLogLog::insert_hash`]. Unlike
+    /// [`HyperLogLog::insert`], the hash value does not depend on any [`Hash`]
+    /// implementation, so that other implementations of xxHash64 may build matching
+    /// sketches from the same bytes, such as UTF-8 strings.
+    ///
+    /// Values inserted with [`HyperLogLog::insert`] are hashed differently, even with the
+    /// default hasher: a hyperloglog should be filled with either method, not both.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let mut hll1 = HyperLogLog::<str>::new();
+    /// hll1.insert_bytes("abc".as_bytes());
+    /// let mut hll2 = HyperLogLog::<str>::new();
+    /// // xxHash64 of "abc" with a zero seed.
+    /// hll2.insert_hash(0x44bc_2cf5_ad77_0999);
+    /// assert_eq!(hll1.histogram(), hll2.histogram());
+    /// ```
+    #[inline]
+    pub fn insert_bytes(&mut self, bytes: &[u8]) {
+        self.insert_hash(hash_bytes(bytes));
+    }
+
+    /// Adds a batch of hash values to the hyperloglog.
+    ///
+    /// This is equivalent to calling [`HyperLogLog::insert_hash`] for each hash value, but
+    /// faster on large batches: register storages such as [`Registers`](crate::Registers)
+    /// split hash values in bulk, and prefetch the registers to update.
     ///
     /// # Examples
     ///
     /// ```
+    /// use std::{collections::hash_map::RandomState, hash::BuildHasher};
+    ///
     /// use hyperloglog::HyperLogLog;
     ///
+    /// let hash_builder = RandomState::new();
+    /// let hashes: Vec<u64> = (0..100_000).map(|i| hash_builder.hash_one(i)).collect();
+    ///
+    /// let mut hll = HyperLogLog::<i32, ()>::with_hasher(());
+    /// hll.insert_hashes(&hashes);
+    /// assert!((50_000..150_000).contains(&hll.len()));
+    /// ```
+    pub fn insert_hashes(&mut self, hashes: &[u64]) {
+        if self.hip.is_some() {
+            // The HIP estimator is updated after each change of registers.
+            for h in hashes {
+                self.insert_hash(*h);
+            }
+        } else {
+            self.registers.update_hashes(hashes);
+        }
+    }
+
+    /// Merges register values into `self`, without checking their number.
+    ///
+    /// This deactivates the HIP estimator, if any.
+    pub(crate) fn merge_values_unchecked(&mut self, values: &[u8]) {
+        for (index, value) in values.iter().enumerate() {
+            if *value > 0 {
+                self.registers.update(index, *value);
+            }
+        }
+        self.hip = None;
+    }
+
+    /// Returns the histogram of register values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let hll = HyperLogLog::<i32>::new();
+    /// assert_eq!(hll.histogram().count(0), 4096);
+    /// ```
+    #[inline]
+    pub fn histogram(&self) -> Histogram {
+        self.registers.histogram()
+    }
+
+    /// Estimates the number of different elements with the given estimator, regardless of
+    /// the hyperloglog's own estimator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{Classic, HyperLogLog, Improved};
+    ///
     /// let mut hll = HyperLogLog::<i32>::new();
     /// for i in 0..100_000 {
     ///     hll.insert(&i);
     /// }
-    /// assert!((50_000..150_000).contains(&hll.len()));
+    /// assert!((50_000.0..150_000.0).contains(&hll.estimate_with(&Classic)));
+    /// assert!((50_000.0..150_000.0).contains(&hll.estimate_with(&Improved)));
     /// ```
-    pub fn len(&self) -> usize {
-        let m = self.registers.len() as f64;
-        let z = 1.
-            / self
-                .registers
-                .iter()
-                .map(|value| 2f64.powi(-(value as i32)))
-                .sum::<f64>();
-        let mut e = self.alpha * m * m * z;
-        if e < 2.5 * m {
-            // Small range correction.
-            let v = self.registers.iter().filter(|value| *value != 0).count();
-            if v != 0 {
-                e = m * (m / (v as f64)).ln();
-            }
-        }
-        // We're using 64-bit hashes, so large range correction is not needed.
-        e as usize
+    #[inline]
+    pub fn estimate_with<F>(&self, estimator: &F) -> f64
+    where
+        F: Estimator + ?Sized,
+    {
+        estimator.estimate(&self.histogram())
+    }
+
+    /// Estimates the number of different elements with the maximum-likelihood method,
+    /// regardless of the hyperloglog's own estimator.
+    ///
+    /// This is the most accurate estimate, but also the most expensive to compute.
+    /// The result reports the number of iterations it took to converge.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let mut hll = HyperLogLog::<i32>::new();
+    /// for i in 0..100_000 {
+    ///     hll.insert(&i);
+    /// }
+    /// let estimate = hll.maximum_likelihood_estimate();
+    /// assert!((50_000.0..150_000.0).contains(&estimate.estimate()));
+    /// ```
+    pub fn maximum_likelihood_estimate(&self) -> MaximumLikelihoodEstimate {
+        MaximumLikelihood.estimate_with_iterations(&self.histogram())
     }
 
     /// Clears the hyperloglog, removing all values.
@@ -250,7 +502,10 @@ where
     /// ```
     #[inline]
     pub fn clear(&mut self) {
-        self.registers.clear()
+        self.registers.clear();
+        if let Some(hip) = &mut self.hip {
+            *hip = Hip::new(&self.registers.histogram(), 0.);
+        }
     }
 
     /// Returns `true` if the hyperloglog contains no elements.
@@ -273,45 +528,286 @@ where
     /// Merges the hyperloglog `rhs` into `self` without checking that precisions and hashers
     /// are the same on both terms.
     ///
+    /// If precisions differ, only the registers found in both hyperloglogs are merged.
+    /// This deactivates the HIP estimator, if any.
+    ///
     /// A checked variant is available via the [`try_merge_from`](HyperLogLog::try_merge_from)
     /// method.
     #[inline]
     pub fn merge_from_unchecked(&mut self, rhs: &Self) {
-        self.registers.merge_from_unchecked(&rhs.registers);
+        self.merge_registers_unchecked(&rhs.registers);
+    }
+
+    /// Merges registers into `self`, without checking their precision.
+    ///
+    /// This deactivates the HIP estimator, if any.
+    #[inline]
+    pub(crate) fn merge_registers_unchecked(&mut self, registers: &R) {
+        self.registers.merge_from_unchecked(registers);
+        self.hip = None;
+    }
+
+    /// Returns the registers of the hyperloglog.
+    #[inline]
+    pub(crate) fn registers(&self) -> &R {
+        &self.registers
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T, S, E> HyperLogLog<T, S, E, Registers>
+where
+    T: ?Sized,
+{
+    /// Returns the layout of the registers of the hyperloglog.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{HyperLogLog, RegisterLayout};
+    ///
+    /// let hll = HyperLogLog::<i32>::new();
+    /// assert_eq!(hll.layout(), RegisterLayout::Byte);
+    /// ```
+    #[inline]
+    pub fn layout(&self) -> RegisterLayout {
+        self.registers.layout()
+    }
+
+    /// Sets the layout of the registers of the hyperloglog, converting them if needed.
+    ///
+    /// The layout does not affect estimation, and hyperloglogs with different layouts
+    /// may be merged together.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{HyperLogLog, RegisterLayout};
+    ///
+    /// let mut hll = HyperLogLog::<i32>::new();
+    /// hll.extend(0..100_000);
+    /// let len = hll.len();
+    /// hll.set_layout(RegisterLayout::Packed);
+    /// assert_eq!(hll.layout(), RegisterLayout::Packed);
+    /// assert_eq!(hll.len(), len);
+    /// ```
+    #[inline]
+    pub fn set_layout(&mut self, layout: RegisterLayout) {
+        self.registers.set_layout(layout);
+    }
+
+    /// Sets the layout of the registers of the hyperloglog, converting them if needed.
+    ///
+    /// See [`set_layout`](HyperLogLog::set_layout) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{HyperLogLog, RegisterLayout};
+    ///
+    /// let hll = HyperLogLog::<i32>::new().with_layout(RegisterLayout::Packed);
+    /// assert_eq!(hll.layout(), RegisterLayout::Packed);
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_layout(mut self, layout: RegisterLayout) -> Self {
+        self.set_layout(layout);
+        self
+    }
+}
+
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
+where
+    T: ?Sized,
+    R: RegisterStorage,
+    E: Estimator,
+{
+    /// Activates the historic inverse probability (HIP) estimator.
+    ///
+    /// The HIP estimator maintains a running estimate, which is increased each time an
+    /// insertion changes a register. For sketches that are only inserted into, it is more
+    /// accurate than any estimator working on the registers alone: its relative standard
+    /// error is about `0.83 / sqrt(m)`, against `1.04 / sqrt(m)`.
+    ///
+    /// The running estimate cannot account for registers changed by a merge, so merging
+    /// deactivates the HIP estimator: from then on, [`len`](HyperLogLog::len) and
+    /// [`estimate`](HyperLogLog::estimate) fall back to the hyperloglog's
+    /// [estimator](HyperLogLog::estimator), as if HIP had never been activated.
+    /// Clearing the hyperloglog keeps the HIP estimator active.
+    ///
+    /// If the hyperloglog is not empty, the running estimate starts from the current
+    /// estimate of the hyperloglog's estimator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let mut hll = HyperLogLog::<i32>::new().with_hip();
+    /// for i in 0..100_000 {
+    ///     hll.insert(&i);
+    /// }
+    /// assert!((50_000..150_000).contains(&hll.len()));
+    ///
+    /// hll.try_merge_from(&HyperLogLog::new()).unwrap();
+    /// assert!(!hll.is_hip_active());
+    /// ```
+    #[must_use]
+    pub fn with_hip(mut self) -> Self {
+        if self.hip.is_none() {
+            let histogram = self.histogram();
+            let estimate = self.estimator.estimate(&histogram);
+            self.hip = Some(Hip::new(&histogram, estimate));
+        }
+        self
+    }
+
+    fn point_estimate(&self) -> f64 {
+        match &self.hip {
+            Some(hip) => hip.estimate(),
+            None => self.estimate_with(&self.estimator),
+        }
+    }
+
+    /// Calculates the approximate number of different elements.
+    ///
+    /// The estimate is computed with the hyperloglog's [estimator](HyperLogLog::estimator),
+    /// or with the HIP estimator if [active](HyperLogLog::is_hip_active).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let mut hll = HyperLogLog::<i32>::new();
+    /// for i in 0..100_000 {
+    ///     hll.insert(&i);
+    /// }
+    /// assert!((50_000..150_000).contains(&hll.len()));
+    /// ```
+    pub fn len(&self) -> usize {
+        self.point_estimate().round() as usize
+    }
+
+    /// Estimates the number of different elements, together with its standard error and
+    /// the bounds of a confidence interval at the given confidence level.
+    ///
+    /// The error is given by the hyperloglog's [estimator](HyperLogLog::estimator) for the
+    /// current registers, such as the error of linear counting for small cardinalities, or
+    /// derived from the precision for the HIP estimator if
+    /// [active](HyperLogLog::is_hip_active).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not strictly between 0 and 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let mut hll = HyperLogLog::<i32>::new();
+    /// for i in 0..100_000 {
+    ///     hll.insert(&i);
+    /// }
+    /// let estimate = hll.estimate(0.95);
+    /// assert!(estimate.lower_bound() <= 100_000.0 && 100_000.0 <= estimate.upper_bound());
+    /// ```
+    pub fn estimate(&self, confidence: f64) -> Estimate {
+        match &self.hip {
+            Some(hip) => Estimate::new(
+                hip.estimate(),
+                Hip::relative_standard_error(self.precision()),
+                confidence,
+            ),
+            None => Estimate::from_histogram(&self.estimator, &self.histogram(), confidence),
+        }
     }
 }
 
-impl<T, S> HyperLogLog<T, S>
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
 where
     T: ?Sized,
+    R: RegisterStorage + Clone,
     S: Clone,
+    E: Clone,
 {
     /// Merges two hyperloglogs without checking that precisions and hashers
     /// are the same on both terms.
     ///
+    /// The HIP estimator is not active on the result.
+    ///
     /// A checked variant is available via the [`try_merge`](HyperLogLog::try_merge) method.
     pub fn merge_unchecked(&self, rhs: &Self) -> Self {
-        Self::with_registers_and_hasher(
-            self.registers.merge_unchecked(&rhs.registers),
-            self.hash_builder.clone(),
-        )
+        let mut registers = self.registers.clone();
+        registers.merge_from_unchecked(&rhs.registers);
+        Self::from_parts(registers, self.hash_builder.clone(), self.estimator.clone())
+    }
+}
+
+/// Number of hash values split, or values hashed, at once by batch insertions.
+pub(crate) const BATCH_SIZE: usize = 64;
+
+/// Number of registers whose maximum over several hyperloglogs is computed at once by
+/// [`HyperLogLog::union_len`].
+const UNION_CHUNK_SIZE: usize = 4096;
+
+/// Splits a hash into the index of a register, and the value it proposes for that register.
+#[inline]
+pub(crate) fn split_hash(precision: Precision, h: u64) -> (usize, u8) {
+    let num_bits = precision.get();
+    // Split h into (w, j) where w represents the (64 - p) upper bits, and j the p lower bits.
+    let w = h >> num_bits;
+    let j = h - (w << num_bits);
+    // Leftmost bit (1-based count).
+    let rho = (w.leading_zeros() + 1 - (num_bits as u32)) as u8;
+    (j as usize, rho)
+}
+
+/// Returns the value of a register once folded from a precision to a lower one, as if its
+/// hash values had been split with the lower precision.
+#[inline]
+pub(crate) fn fold_value(from: Precision, to: Precision, index: usize, value: u8) -> u8 {
+    let (p, q) = (from.get(), to.get());
+    if value < 65 - p {
+        return value;
     }
+    // The upper bits of hash values are all zeros, so the leftmost bit is looked for in the
+    // index bits dropped by the lower precision.
+    let dropped = (index >> q) as u64;
+    65 - q - (64 - dropped.leading_zeros()) as u8
 }
 
-fn check_merge_conds<T, S>(
-    lhs: &HyperLogLog<T, S>,
-    rhs: &HyperLogLog<T, S>,
+pub(crate) fn check_merge_conds<T, S, E, R>(
+    lhs: &HyperLogLog<T, S, E, R>,
+    rhs: &HyperLogLog<T, S, E, R>,
 ) -> Result<(), TryMergeError>
 where
     T: ?Sized,
-    S: Eq,
+    S: HasherIdentity,
+    R: RegisterStorage,
+{
+    check_merge_params(
+        (lhs.precision(), lhs.hasher()),
+        (rhs.precision(), rhs.hasher()),
+    )
+}
+
+/// Checks that registers of the given precisions, filled with the given hashers, may be
+/// merged.
+pub(crate) fn check_merge_params<S>(
+    (lhs_precision, lhs_hasher): (Precision, &S),
+    (rhs_precision, rhs_hasher): (Precision, &S),
+) -> Result<(), TryMergeError>
+where
+    S: HasherIdentity + ?Sized,
 {
-    if lhs.precision() != rhs.precision() {
+    if lhs_precision != rhs_precision {
         return Err(TryMergeError {
             kind: TryMergeErrorKind::Precision,
         });
     }
-    if lhs.hasher() != rhs.hasher() {
+    if lhs_hasher.hasher_id() != rhs_hasher.hasher_id() {
         return Err(TryMergeError {
             kind: TryMergeErrorKind::Hasher,
         });
@@ -319,10 +815,11 @@ where
     Ok(())
 }
 
-impl<T, S> HyperLogLog<T, S>
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
 where
     T: ?Sized,
-    S: Eq,
+    R: RegisterStorage,
+    S: HasherIdentity,
 {
     /// Merges the hyperloglog `rhs` into `self`.
     ///
@@ -349,10 +846,12 @@ where
     }
 }
 
-impl<T, S> HyperLogLog<T, S>
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
 where
     T: ?Sized,
-    S: Clone + Eq,
+    R: RegisterStorage + Clone,
+    S: Clone + HasherIdentity,
+    E: Clone,
 {
     /// Merges two hyperloglogs.
     ///
@@ -378,9 +877,260 @@ where
     }
 }
 
-impl<T, S> HyperLogLog<T, S>
+/// Checks that all hyperloglogs can be merged with the first one, which is returned.
+fn check_union_conds<'a, T, S, E, R, I>(
+    hlls: I,
+) -> Result<Option<&'a HyperLogLog<T, S, E, R>>, TryMergeError>
+where
+    T: ?Sized + 'a,
+    S: HasherIdentity + 'a,
+    E: 'a,
+    R: RegisterStorage + 'a,
+    I: IntoIterator<Item = &'a HyperLogLog<T, S, E, R>>,
+{
+    let mut hlls = hlls.into_iter();
+    let Some(first) = hlls.next() else {
+        return Ok(None);
+    };
+    for hll in hlls {
+        check_merge_conds(first, hll)?;
+    }
+    Ok(Some(first))
+}
+
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
 where
     T: ?Sized,
+    R: RegisterStorage + Clone,
+    S: Clone + HasherIdentity,
+    E: Clone,
+{
+    /// Merges any number of hyperloglogs into a new one, or returns `None` if there are
+    /// none.
+    ///
+    /// All hyperloglogs are checked up front, so that incompatible inputs are rejected
+    /// before any work is done. They are then merged in turn into a copy of the registers
+    /// of the first one, without intermediate hyperloglogs. The result has the hasher, the
+    /// estimator and the register layout of the first hyperloglog.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the precisions or the hashers of the hyperloglogs are not all the same.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let hlls: Vec<_> = (0..10)
+    ///     .map(|i| {
+    ///         let mut hll = HyperLogLog::<i32>::new();
+    ///         hll.extend(i * 10_000..(i + 1) * 10_000);
+    ///         hll
+    ///     })
+    ///     .collect();
+    /// let union = HyperLogLog::union_all(&hlls).unwrap().unwrap();
+    /// assert!((50_000..150_000).contains(&union.len()));
+    /// ```
+    pub fn union_all<'a, I>(hlls: I) -> Result<Option<Self>, TryMergeError>
+    where
+        I: IntoIterator<Item = &'a Self>,
+        I::IntoIter: Clone,
+        Self: 'a,
+    {
+        let hlls = hlls.into_iter();
+        let Some(first) = check_union_conds(hlls.clone())? else {
+            return Ok(None);
+        };
+        let mut registers = first.registers.clone();
+        for hll in hlls.skip(1) {
+            registers.merge_from_unchecked(&hll.registers);
+        }
+        Ok(Some(Self::from_parts(
+            registers,
+            first.hash_builder.clone(),
+            first.estimator.clone(),
+        )))
+    }
+}
+
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
+where
+    T: ?Sized,
+    R: RegisterStorage,
+    S: HasherIdentity,
+    E: Estimator,
+{
+    /// Calculates the approximate number of different elements in the union of any number
+    /// of hyperloglogs, without merging them.
+    ///
+    /// All hyperloglogs are checked up front. The estimate is computed with the
+    /// [estimator](HyperLogLog::estimator) of the first hyperloglog, and is the same as
+    /// [`len`](HyperLogLog::len) of their [union](HyperLogLog::union_all). It is 0 if there
+    /// are no hyperloglogs.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the precisions or the hashers of the hyperloglogs are not all the same.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let hlls: Vec<_> = (0..10)
+    ///     .map(|i| {
+    ///         let mut hll = HyperLogLog::<i32>::new();
+    ///         hll.extend(i * 10_000..(i + 1) * 10_000);
+    ///         hll
+    ///     })
+    ///     .collect();
+    /// let len = HyperLogLog::union_len(&hlls).unwrap();
+    /// assert!((50_000..150_000).contains(&len));
+    /// ```
+    pub fn union_len<'a, I>(hlls: I) -> Result<usize, TryMergeError>
+    where
+        I: IntoIterator<Item = &'a Self>,
+        I::IntoIter: Clone,
+        Self: 'a,
+    {
+        let hlls = hlls.into_iter();
+        let Some(first) = check_union_conds(hlls.clone())? else {
+            return Ok(0);
+        };
+        let len = first.registers.len();
+        let mut counts = [0; NUM_VALUES];
+        let mut values = [0; UNION_CHUNK_SIZE];
+        for start in (0..len).step_by(UNION_CHUNK_SIZE) {
+            let values = &mut values[..UNION_CHUNK_SIZE.min(len - start)];
+            values.fill(0);
+            for hll in hlls.clone() {
+                hll.registers.max_into(start, values);
+            }
+            for (count, chunk_count) in counts.iter_mut().zip(simd::histogram(values)) {
+                *count += chunk_count;
+            }
+        }
+        let histogram = Histogram::new(first.precision(), counts);
+        Ok(first.estimator.estimate(&histogram).round() as usize)
+    }
+}
+
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
+where
+    T: ?Sized,
+    R: RegisterStorage,
+    S: Clone,
+    E: Clone,
+{
+    /// Returns a copy of the hyperloglog with a lower precision.
+    ///
+    /// Registers are folded together, which is lossless: the result is the same as if all
+    /// values had been inserted into a hyperloglog with the lower precision. This
+    /// deactivates the HIP estimator, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `precision` is higher than the precision of the hyperloglog, or if the
+    /// register storage does not support it, such as
+    /// [`InlineRegisters`](crate::InlineRegisters) of another precision.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{HyperLogLog, Precision};
+    ///
+    /// let mut hll = HyperLogLog::<i32>::with_precision(Precision::P14);
+    /// hll.extend(0..100_000);
+    /// let reduced = hll.reduce_precision(Precision::P10);
+    ///
+    /// let mut expected = HyperLogLog::<i32>::with_precision(Precision::P10);
+    /// expected.extend(0..100_000);
+    /// assert_eq!(reduced.histogram(), expected.histogram());
+    /// ```
+    #[must_use]
+    pub fn reduce_precision(&self, precision: Precision) -> Self {
+        Self::from_parts(
+            self.registers.reduce_precision(precision),
+            self.hash_builder.clone(),
+            self.estimator.clone(),
+        )
+    }
+}
+
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
+where
+    T: ?Sized,
+    R: RegisterStorage + Clone,
+    S: Clone + HasherIdentity,
+    E: Clone,
+{
+    /// Merges the hyperloglog `rhs` into `self`, folding both to the lower of their
+    /// precisions.
+    ///
+    /// Unlike [`try_merge_from`](HyperLogLog::try_merge_from), hyperloglogs of different
+    /// precisions may be merged, see [`reduce_precision`](HyperLogLog::reduce_precision).
+    /// Hashers must still be the same.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{HyperLogLog, Precision};
+    ///
+    /// let mut hll1 = HyperLogLog::<i32>::with_precision(Precision::P14);
+    /// hll1.extend(0..75_000);
+    /// let mut hll2 = HyperLogLog::<i32>::with_precision(Precision::P10);
+    /// hll2.extend(25_000..100_000);
+    /// hll1.try_merge_from_folding(&hll2).unwrap();
+    /// assert_eq!(hll1.precision(), Precision::P10);
+    /// assert!((50_000..150_000).contains(&hll1.len()));
+    /// ```
+    pub fn try_merge_from_folding(&mut self, rhs: &Self) -> Result<(), TryMergeError> {
+        if self.hash_builder.hasher_id() != rhs.hash_builder.hasher_id() {
+            return Err(TryMergeError {
+                kind: TryMergeErrorKind::Hasher,
+            });
+        }
+        let precision = self.precision().min(rhs.precision());
+        if self.precision() > precision {
+            *self = self.reduce_precision(precision);
+        }
+        if rhs.precision() > precision {
+            self.merge_registers_unchecked(&rhs.registers.reduce_precision(precision));
+        } else {
+            self.merge_registers_unchecked(&rhs.registers);
+        }
+        Ok(())
+    }
+
+    /// Merges two hyperloglogs, folding both to the lower of their precisions.
+    ///
+    /// See [`try_merge_from_folding`](HyperLogLog::try_merge_from_folding) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{HyperLogLog, Precision};
+    ///
+    /// let mut hll1 = HyperLogLog::<i32>::with_precision(Precision::P10);
+    /// hll1.extend(0..75_000);
+    /// let mut hll2 = HyperLogLog::<i32>::with_precision(Precision::P14);
+    /// hll2.extend(25_000..100_000);
+    /// let hll3 = hll1.try_merge_folding(&hll2).unwrap();
+    /// assert_eq!(hll3.precision(), Precision::P10);
+    /// assert!((50_000..150_000).contains(&hll3.len()));
+    /// ```
+    pub fn try_merge_folding(&self, rhs: &Self) -> Result<Self, TryMergeError> {
+        let mut merged = self.clone();
+        merged.try_merge_from_folding(rhs)?;
+        Ok(merged)
+    }
+}
+
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
+where
+    T: ?Sized,
+    R: RegisterStorage,
     S: BuildHasher,
 {
     /// Adds a value to the hyperloglog.
@@ -398,23 +1148,61 @@ where
         T: Borrow<Q>,
         Q: Hash + ?Sized,
     {
-        let mut hasher = self.hash_builder.build_hasher();
-        value.hash(&mut hasher);
-        let h = hasher.finish();
+        let h = self.hash_builder.hash_one(value);
         self.insert_hash(h);
     }
+
+    /// Adds values to the hyperloglog.
+    ///
+    /// Values are hashed by batches, which are then inserted with
+    /// [`HyperLogLog::insert_hashes`]. This is faster than calling [`HyperLogLog::insert`]
+    /// for each value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let mut hll = HyperLogLog::<i32>::new();
+    /// hll.insert_many(0..100_000);
+    /// hll.insert_many(&[1, 2, 3]);
+    /// assert!((50_000..150_000).contains(&hll.len()));
+    /// ```
+    pub fn insert_many<I>(&mut self, values: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<T>,
+        T: Hash,
+    {
+        let mut hashes = [0; BATCH_SIZE];
+        let mut values = values.into_iter();
+        loop {
+            let mut len = 0;
+            for (h, value) in hashes.iter_mut().zip(&mut values) {
+                *h = self.hash_builder.hash_one(value.borrow());
+                len += 1;
+            }
+            self.insert_hashes(&hashes[..len]);
+            if len < BATCH_SIZE {
+                break;
+            }
+        }
+    }
 }
 
-impl<T, S> Clone for HyperLogLog<T, S>
+impl<T, S, E, R> Clone for HyperLogLog<T, S, E, R>
 where
     T: ?Sized,
+    R: Clone,
     S: Clone,
+    E: Clone,
 {
     fn clone(&self) -> Self {
         Self {
             registers: self.registers.clone(),
             hash_builder: self.hash_builder.clone(),
-            alpha: self.alpha,
+            estimator: self.estimator.clone(),
+            hip: self.hip,
             phantom: PhantomData,
         }
     }
@@ -422,62 +1210,65 @@ where
     fn clone_from(&mut self, source: &Self) {
         self.registers = source.registers.clone();
         self.hash_builder = source.hash_builder.clone();
+        self.estimator = source.estimator.clone();
+        self.hip = source.hip;
     }
 }
 
-impl<T, S> Debug for HyperLogLog<T, S>
+impl<T, S, E, R> Debug for HyperLogLog<T, S, E, R>
 where
     T: ?Sized,
+    R: Debug,
     S: Debug,
+    E: Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("HyperLogLog")
-            .field("alpha", &self.alpha)
             .field("registers", &self.registers)
             .field("hash_builder", &self.hash_builder)
+            .field("estimator", &self.estimator)
+            .field("hip", &self.hip)
             .field("phantom", &self.phantom)
             .finish()
     }
 }
 
-impl<T, S> Default for HyperLogLog<T, S>
+impl<T, S, E, R> Default for HyperLogLog<T, S, E, R>
 where
     T: ?Sized,
+    R: RegisterStorage + Default,
     S: Default,
+    E: Default,
 {
     fn default() -> Self {
-        Self::with_registers_and_hasher(
-            Registers::with_precision(Precision::default()),
-            S::default(),
-        )
+        Self::with_registers_and_hasher(R::default(), S::default())
     }
 }
 
-impl<'a, T, S> Extend<&'a T> for HyperLogLog<T, S>
+impl<'a, T, S, E, R> Extend<&'a T> for HyperLogLog<T, S, E, R>
 where
     T: 'a + Hash + ?Sized,
     S: BuildHasher,
+    R: RegisterStorage,
 {
     fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
-        for value in iter {
-            self.insert(value);
-        }
+        self.insert_many(iter);
     }
 }
 
-impl<T, S> Extend<T> for HyperLogLog<T, S>
+impl<T, S, E, R> Extend<T> for HyperLogLog<T, S, E, R>
 where
     T: Hash,
     S: BuildHasher,
+    R: RegisterStorage,
 {
     fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
-        for value in iter {
-            self.insert(&value);
-        }
+        self.insert_many(iter);
     }
 }
 
-impl<T, const N: usize> From<[T; N]> for HyperLogLog<T, RandomState>
+#[cfg(feature = "alloc")]
+impl<T, const N: usize> From<[T; N]> for HyperLogLog<T>
 where
     T: Hash,
 {
@@ -486,10 +1277,12 @@ where
     }
 }
 
-impl<T, S> FromIterator<T> for HyperLogLog<T, S>
+impl<T, S, E, R> FromIterator<T> for HyperLogLog<T, S, E, R>
 where
     T: Hash,
     S: BuildHasher + Default,
+    E: Default,
+    R: RegisterStorage + Default,
 {
     fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
         let mut hll = HyperLogLog::default();
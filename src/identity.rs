@@ -0,0 +1,155 @@
+use core::hash::{BuildHasher, BuildHasherDefault, Hasher};
+#[cfg(feature = "std")]
+use std::collections::hash_map::RandomState;
+
+use crate::xxhash::BuildXxHash64;
+
+/// Value hashed by hashers whose keys are not otherwise accessible, to fingerprint them.
+const FINGERPRINT_INPUT: u64 = 0x6879_7065_726c_6f67;
+
+/// The identity of a hash function: an algorithm identifier, and a fingerprint of its seed
+/// or keys.
+///
+/// Hyperloglogs may only be merged if their hashers have the same identity.
+///
+/// # Examples
+///
+/// ```
+/// use hyperloglog::{BuildXxHash64, HasherId, HasherIdentity};
+///
+/// let id = BuildXxHash64::with_seed(42).hasher_id();
+/// assert_eq!(id, HasherId::new(HasherId::ALGORITHM_XXHASH64, 42));
+/// assert_ne!(id, BuildXxHash64::with_seed(0).hasher_id());
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct HasherId {
+    algorithm: u32,
+    fingerprint: u64,
+}
+
+impl HasherId {
+    /// Algorithm of `()`, which does not hash anything: hash values are inserted directly.
+    pub const ALGORITHM_NONE: u32 = 0;
+    /// Algorithm of [`BuildXxHash64`], whose fingerprint is the seed.
+    pub const ALGORITHM_XXHASH64: u32 = 1;
+    /// Algorithm of `RandomState`, from the standard library, whose fingerprint is the hash
+    /// of a fixed value.
+    pub const ALGORITHM_RANDOM_STATE: u32 = 2;
+    /// Algorithm of [`BuildHasherDefault`], whose fingerprint is the hash of a fixed value.
+    pub const ALGORITHM_BUILD_HASHER_DEFAULT: u32 = 3;
+    /// First algorithm identifier available to other hashers.
+    pub const ALGORITHM_USER: u32 = 0x8000_0000;
+
+    /// Creates a hasher identity.
+    #[inline]
+    #[must_use]
+    pub const fn new(algorithm: u32, fingerprint: u64) -> Self {
+        Self {
+            algorithm,
+            fingerprint,
+        }
+    }
+
+    /// Creates a hasher identity whose fingerprint is the hash of a fixed value.
+    ///
+    /// This fingerprints hashers whose seed or keys are not accessible: hashers with
+    /// different keys, or whose algorithm changed, have different fingerprints with high
+    /// probability.
+    #[inline]
+    #[must_use]
+    pub fn from_hash<S>(algorithm: u32, hash_builder: &S) -> Self
+    where
+        S: BuildHasher,
+    {
+        let mut hasher = hash_builder.build_hasher();
+        hasher.write_u64(FINGERPRINT_INPUT);
+        Self::new(algorithm, hasher.finish())
+    }
+
+    /// Returns the algorithm identifier.
+    #[inline]
+    pub const fn algorithm(&self) -> u32 {
+        self.algorithm
+    }
+
+    /// Returns the fingerprint of the seed or keys.
+    #[inline]
+    pub const fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+}
+
+/// Hashers reporting a stable identity.
+///
+/// Hyperloglogs are only merged by [`try_merge`](crate::HyperLogLog::try_merge) and
+/// [`try_merge_from`](crate::HyperLogLog::try_merge_from) if their hashers have the same
+/// identity, and the identity is written into
+/// [serialized](crate::HyperLogLog::to_bytes) hyperloglogs.
+///
+/// # Examples
+///
+/// ```
+/// use std::hash::{BuildHasher, DefaultHasher, Hasher};
+///
+/// use hyperloglog::{HasherId, HasherIdentity, HyperLogLog};
+///
+/// struct KeyedHasher {
+///     key: u64,
+/// }
+///
+/// impl BuildHasher for KeyedHasher {
+///     type Hasher = DefaultHasher;
+///
+///     fn build_hasher(&self) -> DefaultHasher {
+///         let mut hasher = DefaultHasher::new();
+///         hasher.write_u64(self.key);
+///         hasher
+///     }
+/// }
+///
+/// impl HasherIdentity for KeyedHasher {
+///     fn hasher_id(&self) -> HasherId {
+///         HasherId::new(HasherId::ALGORITHM_USER, self.key)
+///     }
+/// }
+///
+/// let mut hll1 = HyperLogLog::<i32, _>::with_hasher(KeyedHasher { key: 1 });
+/// let hll2 = HyperLogLog::<i32, _>::with_hasher(KeyedHasher { key: 2 });
+/// assert!(hll1.try_merge_from(&hll2).is_err());
+/// ```
+pub trait HasherIdentity {
+    /// Returns the identity of the hasher.
+    fn hasher_id(&self) -> HasherId;
+}
+
+impl HasherIdentity for () {
+    #[inline]
+    fn hasher_id(&self) -> HasherId {
+        HasherId::new(HasherId::ALGORITHM_NONE, 0)
+    }
+}
+
+impl HasherIdentity for BuildXxHash64 {
+    #[inline]
+    fn hasher_id(&self) -> HasherId {
+        HasherId::new(HasherId::ALGORITHM_XXHASH64, self.seed())
+    }
+}
+
+impl<H> HasherIdentity for BuildHasherDefault<H>
+where
+    H: Default + Hasher,
+{
+    #[inline]
+    fn hasher_id(&self) -> HasherId {
+        HasherId::from_hash(HasherId::ALGORITHM_BUILD_HASHER_DEFAULT, self)
+    }
+}
+
+#[cfg(feature = "std")]
+impl HasherIdentity for RandomState {
+    #[inline]
+    fn hasher_id(&self) -> HasherId {
+        HasherId::from_hash(HasherId::ALGORITHM_RANDOM_STATE, self)
+    }
+}
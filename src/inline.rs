@@ -0,0 +1,207 @@
+use core::{fmt::Debug, hash::Hash};
+
+use crate::{
+    estimator::EstimationStrategy,
+    histogram::Histogram,
+    hyperloglog::{DefaultBuildHasher, HyperLogLog},
+    precision::Precision,
+    simd,
+    storage::RegisterStorage,
+};
+
+/// A hyperloglog whose precision is known at compile time, and whose registers are stored
+/// inline, without any heap allocation.
+///
+/// As the precision is part of the type, merging hyperloglogs of different precisions does
+/// not compile.
+///
+/// # Examples
+///
+/// ```
+/// use hyperloglog::InlineHyperLogLog;
+///
+/// let mut hll = InlineHyperLogLog::<i32, 10>::new();
+/// for i in 0..100_000 {
+///     hll.insert(&i);
+/// }
+/// assert!((50_000..150_000).contains(&hll.len()));
+/// ```
+///
+/// ```compile_fail
+/// use hyperloglog::InlineHyperLogLog;
+///
+/// let hll1 = InlineHyperLogLog::<i32, 8>::new();
+/// let hll2 = InlineHyperLogLog::<i32, 10>::new();
+/// hll1.try_merge(&hll2);
+/// ```
+pub type InlineHyperLogLog<T, const P: u8, S = DefaultBuildHasher, E = EstimationStrategy> =
+    HyperLogLog<T, S, E, InlineRegisters<P>>;
+
+/// A precision known at compile time.
+///
+/// It is only used to select the register array of [`InlineRegisters`], through the
+/// [`InlineArray`] trait.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ConstPrecision<const P: u8>;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Register array for a precision known at compile time.
+///
+/// This trait is sealed, and implemented by [`ConstPrecision<P>`] for all supported
+/// precisions, from 4 to 18.
+pub trait InlineArray: private::Sealed {
+    /// The precision.
+    const PRECISION: Precision;
+
+    /// An array of `2^p` registers.
+    type Array: AsRef<[u8]> + AsMut<[u8]> + Copy + Debug + Eq + Hash;
+
+    /// Returns an array of registers set to zero.
+    fn zeroed() -> Self::Array;
+}
+
+macro_rules! impl_inline_array {
+    ($($p:literal),*) => {
+        $(
+            impl private::Sealed for ConstPrecision<$p> {}
+
+            impl InlineArray for ConstPrecision<$p> {
+                const PRECISION: Precision = match Precision::new($p) {
+                    Some(precision) => precision,
+                    None => unreachable!(),
+                };
+
+                type Array = [u8; 1 << $p];
+
+                #[inline]
+                fn zeroed() -> Self::Array {
+                    [0; 1 << $p]
+                }
+            }
+        )*
+    };
+}
+
+impl_inline_array!(4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18);
+
+/// Registers stored inline in an array of `2^P` bytes.
+///
+/// Unlike [`Registers`](crate::Registers), they do not keep the histogram of their values,
+/// so estimating the number of elements goes through all registers.
+///
+/// # Examples
+///
+/// ```
+/// use hyperloglog::{InlineRegisters, RegisterStorage};
+///
+/// let mut registers = InlineRegisters::<8>::default();
+/// registers.update(3, 5);
+/// assert_eq!(registers.get(3), 5);
+/// assert_eq!(std::mem::size_of_val(&registers), 256);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct InlineRegisters<const P: u8>
+where
+    ConstPrecision<P>: InlineArray,
+{
+    values: <ConstPrecision<P> as InlineArray>::Array,
+}
+
+impl<const P: u8> Default for InlineRegisters<P>
+where
+    ConstPrecision<P>: InlineArray,
+{
+    #[inline]
+    fn default() -> Self {
+        Self {
+            values: ConstPrecision::<P>::zeroed(),
+        }
+    }
+}
+
+impl<const P: u8> RegisterStorage for InlineRegisters<P>
+where
+    ConstPrecision<P>: InlineArray,
+{
+    /// Creates registers set to zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `precision` is not `P`.
+    fn with_precision(precision: Precision) -> Self {
+        assert_eq!(
+            precision,
+            ConstPrecision::<P>::PRECISION,
+            "precision must be P{P}"
+        );
+        Self::default()
+    }
+
+    /// Returns `P`.
+    #[inline]
+    fn default_precision() -> Precision {
+        ConstPrecision::<P>::PRECISION
+    }
+
+    /// Returns `true` if `precision` is `P`.
+    #[inline]
+    fn supports_precision(precision: Precision) -> bool {
+        precision == ConstPrecision::<P>::PRECISION
+    }
+
+    #[inline]
+    fn precision(&self) -> Precision {
+        ConstPrecision::<P>::PRECISION
+    }
+
+    #[inline]
+    fn get(&self, index: usize) -> u8 {
+        self.values.as_ref()[index]
+    }
+
+    #[inline]
+    fn update(&mut self, index: usize, value: u8) -> u8 {
+        let current_p = &mut self.values.as_mut()[index];
+        let previous = *current_p;
+        if previous < value {
+            *current_p = value;
+        }
+        previous
+    }
+
+    #[inline]
+    fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        self.values.as_ref().iter().copied()
+    }
+
+    #[inline]
+    fn max_into(&self, start: usize, values: &mut [u8]) {
+        simd::max_assign(
+            values,
+            self.values.as_ref().get(start..).unwrap_or_default(),
+        );
+    }
+
+    #[inline]
+    fn clear(&mut self) {
+        self.values.as_mut().fill(0);
+    }
+
+    #[inline]
+    fn histogram(&self) -> Histogram {
+        Histogram::new(self.precision(), simd::histogram(self.values.as_ref()))
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        simd::count_zeros(self.values.as_ref()) == self.len()
+    }
+
+    #[inline]
+    fn merge_from_unchecked(&mut self, rhs: &Self) {
+        simd::max_assign(self.values.as_mut(), rhs.values.as_ref());
+    }
+}
@@ -0,0 +1,600 @@
+//! Joint estimation over the registers of two hyperloglogs.
+//!
+//! Two sets `A` and `B` are split into the disjoint sets `A \ B`, `B \ A` and `A ∩ B`, whose
+//! numbers of elements are estimated at once by maximizing the joint likelihood of both
+//! register arrays, as described by Otmar Ertl in *New cardinality estimation algorithms
+//! for HyperLogLog sketches* (2017).
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
+use crate::{
+    error::TryMergeError,
+    estimate::Estimate,
+    estimator::{Estimator, MaximumLikelihood},
+    histogram::{Histogram, NUM_VALUES},
+    hyperloglog::{check_merge_conds, HyperLogLog},
+    identity::HasherIdentity,
+    precision::Precision,
+    storage::RegisterStorage,
+};
+
+/// Upper bound on the number of Newton iterations.
+const MAX_ITERATIONS: usize = 100;
+
+/// Step, in log-rates, below which iterations stop.
+const TOLERANCE: f64 = 1e-9;
+
+/// Largest step, in log-rates, taken by an iteration.
+const MAX_STEP: f64 = 4.;
+
+/// Smallest rate per register, below which a set is considered empty.
+const MIN_RATE: f64 = 1e-12;
+
+/// Relative step of the finite differences approximating second derivatives.
+const DIFFERENCE_STEP: f64 = 1e-5;
+
+/// Statistics of the registers of two hyperloglogs, sufficient for the joint likelihood.
+///
+/// Each pair of registers of the same index is counted by its values, depending on which
+/// of them is the largest.
+pub(crate) struct JointStatistics {
+    precision: Precision,
+    /// Values of the left registers, where lower than the right ones.
+    less_lhs: [u32; NUM_VALUES],
+    /// Values of the right registers, where greater than the left ones.
+    less_rhs: [u32; NUM_VALUES],
+    /// Values of the left registers, where greater than the right ones.
+    greater_lhs: [u32; NUM_VALUES],
+    /// Values of the right registers, where lower than the left ones.
+    greater_rhs: [u32; NUM_VALUES],
+    /// Values of equal registers.
+    equal: [u32; NUM_VALUES],
+}
+
+impl JointStatistics {
+    /// Counts pairs of registers of the same precision.
+    pub(crate) fn new<L, R>(precision: Precision, lhs: L, rhs: R) -> Self
+    where
+        L: Iterator<Item = u8>,
+        R: Iterator<Item = u8>,
+    {
+        let mut statistics = Self {
+            precision,
+            less_lhs: [0; NUM_VALUES],
+            less_rhs: [0; NUM_VALUES],
+            greater_lhs: [0; NUM_VALUES],
+            greater_rhs: [0; NUM_VALUES],
+            equal: [0; NUM_VALUES],
+        };
+        for (lhs_value, rhs_value) in lhs.zip(rhs) {
+            let (k1, k2) = (lhs_value as usize, rhs_value as usize);
+            if k1 < k2 {
+                statistics.less_lhs[k1] += 1;
+                statistics.less_rhs[k2] += 1;
+            } else if k1 > k2 {
+                statistics.greater_lhs[k1] += 1;
+                statistics.greater_rhs[k2] += 1;
+            } else {
+                statistics.equal[k1] += 1;
+            }
+        }
+        statistics
+    }
+
+    /// Returns the histograms of the left registers, of the right registers, and of their
+    /// union.
+    fn histograms(&self) -> [Histogram; 3] {
+        let mut lhs = self.equal;
+        let mut rhs = self.equal;
+        let mut union = self.equal;
+        for k in 0..NUM_VALUES {
+            lhs[k] += self.less_lhs[k] + self.greater_lhs[k];
+            rhs[k] += self.less_rhs[k] + self.greater_rhs[k];
+            union[k] += self.less_rhs[k] + self.greater_lhs[k];
+        }
+        [lhs, rhs, union].map(|counts| Histogram::new(self.precision, counts))
+    }
+
+    /// Returns the joint log-likelihood of rates per register `[a, b, x]`, of `A \ B`,
+    /// `B \ A` and `A ∩ B` respectively, together with its gradient.
+    fn log_likelihood(&self, rates: [f64; 3]) -> (f64, [f64; 3]) {
+        let [a, b, x] = rates;
+        let q = 64 - self.precision.get() as usize;
+        let (mut value, mut gradient) = (0., [0.; 3]);
+        for k in 0..=q + 1 {
+            let mut add = |count: u32, terms: (f64, [f64; 3])| {
+                if count != 0 {
+                    let count = count as f64;
+                    value += count * terms.0;
+                    for (g, dg) in gradient.iter_mut().zip(terms.1) {
+                        *g += count * dg;
+                    }
+                }
+            };
+            let (ln, derivative) = ln_mass(a + x, k, q);
+            add(self.less_lhs[k], (ln, [derivative, 0., derivative]));
+            let (ln, derivative) = ln_mass(b, k, q);
+            add(self.less_rhs[k], (ln, [0., derivative, 0.]));
+            let (ln, derivative) = ln_mass(a, k, q);
+            add(self.greater_lhs[k], (ln, [derivative, 0., 0.]));
+            let (ln, derivative) = ln_mass(b + x, k, q);
+            add(self.greater_rhs[k], (ln, [0., derivative, derivative]));
+            add(self.equal[k], ln_equal_mass(rates, k, q));
+        }
+        (value, gradient)
+    }
+
+    /// Approximates the Hessian of the log-likelihood by finite differences of its
+    /// gradient.
+    ///
+    /// Steps are relative to the total rate, so that they stay accurate for rates close to
+    /// zero, where differences are taken forward.
+    fn hessian(&self, rates: [f64; 3]) -> [[f64; 3]; 3] {
+        let h = DIFFERENCE_STEP * rates.iter().sum::<f64>();
+        let mut hessian = [[0.; 3]; 3];
+        for j in 0..3 {
+            let (mut lower, mut upper) = (rates, rates);
+            if rates[j] > h {
+                lower[j] -= h;
+            }
+            upper[j] += h;
+            let (_, lower_gradient) = self.log_likelihood(lower);
+            let (_, upper_gradient) = self.log_likelihood(upper);
+            for i in 0..3 {
+                hessian[i][j] = (upper_gradient[i] - lower_gradient[i]) / (upper[j] - lower[j]);
+            }
+        }
+        core::array::from_fn(|i| core::array::from_fn(|j| (hessian[i][j] + hessian[j][i]) / 2.))
+    }
+
+    /// Estimates the numbers of elements of `A \ B`, `B \ A` and `A ∩ B`, together with
+    /// confidence intervals at the given confidence level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not strictly between 0 and 1.
+    pub(crate) fn estimate(&self, confidence: f64) -> JointEstimate {
+        let m = (1u64 << self.precision.get()) as f64;
+        let [lhs, rhs, union] = self.histograms().map(|histogram| {
+            let estimate = MaximumLikelihood.estimate(&histogram);
+            // Saturated registers hold no information on the number of elements.
+            if estimate.is_finite() {
+                estimate
+            } else {
+                f64::MAX
+            }
+        });
+        if union == 0. {
+            return JointEstimate::new([0.; 3], [[0.; 3]; 3], confidence);
+        }
+        // Marginal estimates give a starting point, by inclusion–exclusion.
+        let initial = [union - rhs, union - lhs, lhs + rhs - union];
+        let mut log_rates = initial.map(|n| (n.max(1.) / m).ln());
+        let rates_of = |log_rates: [f64; 3]| log_rates.map(f64::exp);
+
+        let (mut value, mut gradient) = self.log_likelihood(rates_of(log_rates));
+        let mut damping = 0.;
+        for _ in 0..MAX_ITERATIONS {
+            // Newton's method on log-rates, whose Hessian follows from the one on rates.
+            let rates = rates_of(log_rates);
+            let hessian = self.hessian(rates);
+            let log_gradient: [f64; 3] = core::array::from_fn(|i| rates[i] * gradient[i]);
+            let mut information = [[0.; 3]; 3];
+            for i in 0..3 {
+                for j in 0..3 {
+                    information[i][j] = -rates[i] * rates[j] * hessian[i][j];
+                }
+                information[i][i] -= log_gradient[i];
+            }
+            // The step is damped until it increases the likelihood, as in the
+            // Levenberg–Marquardt algorithm.
+            let mut accepted = None;
+            for _ in 0..MAX_ITERATIONS {
+                let mut damped = information;
+                for (i, row) in damped.iter_mut().enumerate() {
+                    row[i] += damping;
+                }
+                if let Some(step) = solve(damped, log_gradient) {
+                    let step = step.map(|s| s.clamp(-MAX_STEP, MAX_STEP));
+                    let candidate: [f64; 3] =
+                        core::array::from_fn(|i| (log_rates[i] + step[i]).max((MIN_RATE / m).ln()));
+                    let (candidate_value, candidate_gradient) =
+                        self.log_likelihood(rates_of(candidate));
+                    if candidate_value >= value {
+                        accepted = Some((candidate, candidate_value, candidate_gradient));
+                        break;
+                    }
+                }
+                let scale = information
+                    .iter()
+                    .enumerate()
+                    .fold(1., |scale: f64, (i, row)| scale.max(row[i].abs()));
+                damping = (damping * 10.).max(1e-6 * scale);
+            }
+            let Some((candidate, candidate_value, candidate_gradient)) = accepted else {
+                break;
+            };
+            let step = (0..3).fold(0., |step: f64, i| {
+                step.max((candidate[i] - log_rates[i]).abs())
+            });
+            log_rates = candidate;
+            value = candidate_value;
+            gradient = candidate_gradient;
+            damping /= 10.;
+            if step < TOLERANCE {
+                break;
+            }
+        }
+
+        // The covariance of rates is the inverse of the observed information. It is
+        // singular if a hyperloglog is empty, in which case the numbers of elements it
+        // holds no information on are known to be zero.
+        let rates = rates_of(log_rates);
+        let hessian = self.hessian(rates);
+        let information = hessian.map(|row| row.map(|h| -h));
+        let covariance = invert(information).unwrap_or_else(|| {
+            core::array::from_fn(|i| {
+                core::array::from_fn(|j| {
+                    if i == j && information[i][i] > 0. {
+                        1. / information[i][i]
+                    } else {
+                        0.
+                    }
+                })
+            })
+        });
+        JointEstimate::new(
+            rates.map(|rate| if rate <= MIN_RATE { 0. } else { m * rate }),
+            covariance.map(|row| row.map(|c| m * m * c)),
+            confidence,
+        )
+    }
+}
+
+/// Estimates comparing two sets `A` and `B`, computed at once from the registers of their
+/// hyperloglogs.
+///
+/// It is returned by [`HyperLogLog::try_joint_estimate`]. The numbers of elements of the
+/// disjoint sets `A \ B`, `B \ A` and `A ∩ B` maximize the joint likelihood of both
+/// register arrays, and the other estimates are derived from them, so that they are
+/// consistent with each other. Standard errors follow from the observed information.
+///
+/// # Examples
+///
+/// ```
+/// use hyperloglog::HyperLogLog;
+///
+/// let mut hll1 = HyperLogLog::<i32>::new();
+/// hll1.extend(0..100_000);
+/// let mut hll2 = HyperLogLog::<i32>::new();
+/// hll2.extend(50_000..200_000);
+/// let estimate = hll1.try_joint_estimate(&hll2, 0.95).unwrap();
+/// assert!((45_000.0..55_000.0).contains(&estimate.lhs_difference().value()));
+/// assert!((90_000.0..110_000.0).contains(&estimate.rhs_difference().value()));
+/// assert!((0.22..0.28).contains(&estimate.jaccard()));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JointEstimate {
+    lhs_difference: Estimate,
+    rhs_difference: Estimate,
+    intersection: Estimate,
+    union: Estimate,
+    jaccard: f64,
+    jaccard_standard_error: f64,
+}
+
+impl JointEstimate {
+    /// Creates estimates from the numbers of elements of `A \ B`, `B \ A` and `A ∩ B`,
+    /// their covariance matrix, and a confidence level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not strictly between 0 and 1.
+    fn new(values: [f64; 3], covariance: [[f64; 3]; 3], confidence: f64) -> Self {
+        // Variance of a linear combination of the numbers of elements.
+        let variance = |weights: [f64; 3]| {
+            let mut variance = 0.;
+            for (row, wi) in covariance.iter().zip(weights) {
+                for (c, wj) in row.iter().zip(weights) {
+                    variance += wi * wj * c;
+                }
+            }
+            variance.max(0.)
+        };
+        let estimate = |weights: [f64; 3]| {
+            let value = (0..3).map(|i| weights[i] * values[i]).sum();
+            Estimate::with_standard_error(value, variance(weights).sqrt(), confidence)
+        };
+        let [a, b, x] = values;
+        let union = a + b + x;
+        let (jaccard, jaccard_standard_error) = if union > 0. {
+            // Delta method, from the gradient of x / (a + b + x).
+            let gradient = [-x, -x, a + b].map(|g| g / (union * union));
+            (x / union, variance(gradient).sqrt())
+        } else {
+            (0., 0.)
+        };
+        Self {
+            lhs_difference: estimate([1., 0., 0.]),
+            rhs_difference: estimate([0., 1., 0.]),
+            intersection: estimate([0., 0., 1.]),
+            union: estimate([1.; 3]),
+            jaccard,
+            jaccard_standard_error,
+        }
+    }
+
+    /// Returns the estimated number of elements in `A` but not in `B`, that is `|A \ B|`.
+    #[inline]
+    pub fn lhs_difference(&self) -> Estimate {
+        self.lhs_difference
+    }
+
+    /// Returns the estimated number of elements in `B` but not in `A`, that is `|B \ A|`.
+    #[inline]
+    pub fn rhs_difference(&self) -> Estimate {
+        self.rhs_difference
+    }
+
+    /// Returns the estimated number of elements in both `A` and `B`, that is `|A ∩ B|`.
+    #[inline]
+    pub fn intersection(&self) -> Estimate {
+        self.intersection
+    }
+
+    /// Returns the estimated number of elements in `A` or `B`, that is `|A ∪ B|`.
+    #[inline]
+    pub fn union(&self) -> Estimate {
+        self.union
+    }
+
+    /// Returns the estimated Jaccard index `|A ∩ B| / |A ∪ B|`, or 0 if both sets are
+    /// empty.
+    #[inline]
+    pub fn jaccard(&self) -> f64 {
+        self.jaccard
+    }
+
+    /// Returns the standard error of the Jaccard index.
+    #[inline]
+    pub fn jaccard_standard_error(&self) -> f64 {
+        self.jaccard_standard_error
+    }
+}
+
+/// Returns `2^-k`, or 0 for the largest register value `q + 1`.
+#[inline]
+fn rho(k: usize, q: usize) -> f64 {
+    if k <= q {
+        2f64.powi(-(k as i32))
+    } else {
+        0.
+    }
+}
+
+/// Returns the logarithm of the probability that a register takes the value `k`, given a
+/// rate of elements per register, together with its derivative with respect to the rate.
+///
+/// The probability is `e^(-rate * rho(k)) - e^(-rate * rho(k - 1))`.
+#[inline]
+fn ln_mass(rate: f64, k: usize, q: usize) -> (f64, f64) {
+    if k == 0 {
+        return (-rate, -1.);
+    }
+    let (rho, d) = (rho(k, q), rho(k - 1, q) - rho(k, q));
+    // 1 - e^(-rate * d), accurately for small rates.
+    let l = -(-rate * d).exp_m1();
+    (-rate * rho + l.ln(), -rho + d * (1. - l) / l)
+}
+
+/// Returns the logarithm of the probability that two registers both take the value `k`,
+/// given the rates per register `[a, b, x]`, together with its gradient.
+#[inline]
+fn ln_equal_mass(rates: [f64; 3], k: usize, q: usize) -> (f64, [f64; 3]) {
+    let [a, b, x] = rates;
+    if k == 0 {
+        return (-(a + b + x), [-1.; 3]);
+    }
+    let (rho, d) = (rho(k, q), rho(k - 1, q) - rho(k, q));
+    // The probability is e^(-(a + b + x) * rho(k)) * h, with h factored so that it is
+    // accurate for small rates.
+    let [la, lb, lx] = [a, b, x].map(|rate| -(-rate * d).exp_m1());
+    let h = (1. - lx) * la * lb + lx;
+    let gradient = [
+        -rho + d * (1. - lx) * (1. - la) * lb / h,
+        -rho + d * (1. - lx) * la * (1. - lb) / h,
+        -rho + d * (1. - lx) * (1. - la * lb) / h,
+    ];
+    (-(a + b + x) * rho + h.ln(), gradient)
+}
+
+/// Solves `m * y = v` for a symmetric positive definite matrix `m`, with a Cholesky
+/// decomposition.
+fn solve(m: [[f64; 3]; 3], v: [f64; 3]) -> Option<[f64; 3]> {
+    let mut l = [[0.; 3]; 3];
+    for i in 0..3 {
+        for j in 0..=i {
+            let sum = m[i][j] - (0..j).map(|k| l[i][k] * l[j][k]).sum::<f64>();
+            if i == j {
+                if sum.is_nan() || sum <= 0. {
+                    return None;
+                }
+                l[i][i] = sum.sqrt();
+            } else {
+                l[i][j] = sum / l[j][j];
+            }
+        }
+    }
+    let mut y = [0.; 3];
+    for i in 0..3 {
+        y[i] = (v[i] - (0..i).map(|k| l[i][k] * y[k]).sum::<f64>()) / l[i][i];
+    }
+    for i in (0..3).rev() {
+        y[i] = (y[i] - (i + 1..3).map(|k| l[k][i] * y[k]).sum::<f64>()) / l[i][i];
+    }
+    Some(y)
+}
+
+/// Inverts a symmetric positive definite matrix.
+fn invert(m: [[f64; 3]; 3]) -> Option<[[f64; 3]; 3]> {
+    let mut inverse = [[0.; 3]; 3];
+    for j in 0..3 {
+        let mut unit = [0.; 3];
+        unit[j] = 1.;
+        let column = solve(m, unit)?;
+        for i in 0..3 {
+            inverse[i][j] = column[i];
+        }
+    }
+    Some(inverse)
+}
+
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
+where
+    T: ?Sized,
+    S: HasherIdentity,
+    R: RegisterStorage,
+{
+    /// Estimates the numbers of elements in `self` but not in `rhs`, in `rhs` but not in
+    /// `self`, in both, and in either, together with their Jaccard index, from one joint
+    /// computation over both register arrays.
+    ///
+    /// This is more accurate than combining [`len`](HyperLogLog::len) of each hyperloglog
+    /// and of their union, whose errors add up. Estimates come with the bounds of a
+    /// confidence interval at the given confidence level.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the precisions or the hashers of the hyperloglogs are not the same.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not strictly between 0 and 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let mut hll1 = HyperLogLog::<i32>::new();
+    /// hll1.extend(0..100_000);
+    /// let mut hll2 = HyperLogLog::<i32>::new();
+    /// hll2.extend(0..50_000);
+    /// let estimate = hll1.try_joint_estimate(&hll2, 0.95).unwrap();
+    /// assert!(estimate.rhs_difference().value() < 2_000.0);
+    /// assert!((0.45..0.55).contains(&estimate.jaccard()));
+    /// ```
+    pub fn try_joint_estimate(
+        &self,
+        rhs: &Self,
+        confidence: f64,
+    ) -> Result<JointEstimate, TryMergeError> {
+        check_merge_conds(self, rhs)?;
+        let statistics = JointStatistics::new(
+            self.precision(),
+            self.registers().iter(),
+            rhs.registers().iter(),
+        );
+        Ok(statistics.estimate(confidence))
+    }
+
+    /// Estimates the number of elements in both `self` and `rhs`, together with its
+    /// standard error and the bounds of a confidence interval at the given confidence
+    /// level.
+    ///
+    /// The estimate maximizes the joint likelihood of both register arrays, which is much
+    /// more accurate than
+    /// [inclusion–exclusion](HyperLogLog::try_intersection_inclusion_exclusion), especially
+    /// for small intersections. It is the
+    /// [intersection](JointEstimate::intersection) of
+    /// [`try_joint_estimate`](HyperLogLog::try_joint_estimate).
+    ///
+    /// # Errors
+    ///
+    /// Fails if the precisions or the hashers of the hyperloglogs are not the same.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not strictly between 0 and 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let mut hll1 = HyperLogLog::<i32>::new();
+    /// hll1.extend(0..100_000);
+    /// let mut hll2 = HyperLogLog::<i32>::new();
+    /// hll2.extend(95_000..200_000);
+    /// let estimate = hll1.try_intersection(&hll2, 0.95).unwrap();
+    /// assert!((2_500.0..7_500.0).contains(&estimate.value()));
+    /// ```
+    pub fn try_intersection(&self, rhs: &Self, confidence: f64) -> Result<Estimate, TryMergeError> {
+        Ok(self.try_joint_estimate(rhs, confidence)?.intersection())
+    }
+}
+
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
+where
+    T: ?Sized,
+    R: RegisterStorage + Clone,
+    S: Clone + HasherIdentity,
+    E: Clone + Estimator,
+{
+    /// Estimates the number of elements in both `self` and `rhs` by inclusion–exclusion,
+    /// that is, as `|A| + |B| - |A ∪ B|`, together with its standard error and the bounds
+    /// of a confidence interval at the given confidence level.
+    ///
+    /// All three terms are estimated by the estimator of `self`, and the union is
+    /// estimated from [`merge_unchecked`](HyperLogLog::merge_unchecked). The estimate is
+    /// clamped between 0 and the smallest of `|A|` and `|B|`. Its standard error assumes
+    /// that the errors of the three terms are independent, so it is only indicative: the
+    /// error is large relative to small intersections, for which
+    /// [`try_intersection`](HyperLogLog::try_intersection) is much more accurate.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the precisions or the hashers of the hyperloglogs are not the same.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not strictly between 0 and 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let mut hll1 = HyperLogLog::<i32>::new();
+    /// hll1.extend(0..100_000);
+    /// let mut hll2 = HyperLogLog::<i32>::new();
+    /// hll2.extend(50_000..150_000);
+    /// let estimate = hll1
+    ///     .try_intersection_inclusion_exclusion(&hll2, 0.95)
+    ///     .unwrap();
+    /// assert!((25_000.0..75_000.0).contains(&estimate.value()));
+    /// ```
+    pub fn try_intersection_inclusion_exclusion(
+        &self,
+        rhs: &Self,
+        confidence: f64,
+    ) -> Result<Estimate, TryMergeError> {
+        check_merge_conds(self, rhs)?;
+        let union = self.merge_unchecked(rhs);
+        let [lhs, rhs, union] =
+            [&self.registers(), &rhs.registers(), &union.registers()].map(|registers| {
+                Estimate::from_histogram(self.estimator(), &registers.histogram(), confidence)
+            });
+        let standard_error = [lhs, rhs, union]
+            .iter()
+            .map(|estimate| estimate.standard_error().powi(2))
+            .sum::<f64>()
+            .sqrt();
+        let [lhs, rhs, union] = [lhs, rhs, union].map(|estimate| estimate.value());
+        Ok(Estimate::with_standard_error(
+            (lhs + rhs - union).min(lhs.min(rhs)).max(0.),
+            standard_error,
+            confidence,
+        ))
+    }
+}
@@ -9,13 +9,80 @@
 //! }
 //! assert!((50_000..150_000).contains(&hll.len()));
 //! ```
+//!
+//! # Features
+//!
+//! - `std` (enabled by default): uses the standard library for floating-point math, and to
+//!   detect CPU features at runtime.
+//! - `alloc` (enabled by `std`): provides the heap-allocated [`Registers`], which are the
+//!   default register storage of [`HyperLogLog`].
+//! - `rayon`: implements `ParallelExtend` and `FromParallelIterator` for [`HyperLogLog`],
+//!   and provides `HyperLogLog::try_par_merge_from`, to insert and merge with several
+//!   threads.
+//!
+//! Without `std`, the crate is `no_std`. Without `alloc` either, [`HyperLogLog`] has no
+//! default type parameters, and registers must be stored inline, as in
+//! [`InlineHyperLogLog`].
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(all(feature = "alloc", target_has_atomic = "8"))]
+pub use crate::concurrent::ConcurrentHyperLogLog;
+#[cfg(feature = "alloc")]
+pub use crate::registers::{RegisterLayout, Registers};
 pub use crate::{
-    error::{TryFromIntError, TryMergeError, TryMergeErrorKind},
+    error::{
+        FromBytesError, FromBytesErrorKind, TryFromIntError, TryFromSliceError, TryMergeError,
+        TryMergeErrorKind,
+    },
+    estimate::Estimate,
+    estimator::{
+        Classic, EstimationStrategy, Estimator, HyperLogLogPlusPlus, Improved, MaximumLikelihood,
+        MaximumLikelihoodEstimate,
+    },
+    histogram::Histogram,
     hyperloglog::HyperLogLog,
+    identity::{HasherId, HasherIdentity},
+    inline::{ConstPrecision, InlineArray, InlineHyperLogLog, InlineRegisters},
+    joint::JointEstimate,
     precision::Precision,
+    storage::RegisterStorage,
+    view::{HyperLogLogMut, HyperLogLogRef},
+    xxhash::{BuildXxHash64, XxHash64},
 };
 
+mod bias;
+#[cfg(feature = "alloc")]
+mod compact;
+#[cfg(all(feature = "alloc", target_has_atomic = "8"))]
+mod concurrent;
 mod error;
+mod estimate;
+mod estimator;
+mod hip;
+mod histogram;
 mod hyperloglog;
+mod identity;
+mod inline;
+mod joint;
+#[cfg(not(any(feature = "std", test)))]
+mod math;
+#[cfg(feature = "alloc")]
+mod packed;
+#[cfg(feature = "rayon")]
+mod parallel;
 mod precision;
+#[cfg(feature = "alloc")]
 mod registers;
+#[cfg(feature = "alloc")]
+mod serial;
+mod simd;
+#[cfg(feature = "alloc")]
+mod sparse;
+mod storage;
+mod view;
+mod xxhash;
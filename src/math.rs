@@ -0,0 +1,42 @@
+//! Floating-point functions that `core` lacks, implemented with `libm` without `std`.
+
+pub(crate) trait Float {
+    fn sqrt(self) -> Self;
+    fn ln(self) -> Self;
+    fn exp(self) -> Self;
+    fn exp_m1(self) -> Self;
+    fn powi(self, n: i32) -> Self;
+    fn round(self) -> Self;
+}
+
+impl Float for f64 {
+    #[inline]
+    fn sqrt(self) -> Self {
+        libm::sqrt(self)
+    }
+
+    #[inline]
+    fn ln(self) -> Self {
+        libm::log(self)
+    }
+
+    #[inline]
+    fn exp(self) -> Self {
+        libm::exp(self)
+    }
+
+    #[inline]
+    fn exp_m1(self) -> Self {
+        libm::expm1(self)
+    }
+
+    #[inline]
+    fn powi(self, n: i32) -> Self {
+        libm::pow(self, n.into())
+    }
+
+    #[inline]
+    fn round(self) -> Self {
+        libm::round(self)
+    }
+}
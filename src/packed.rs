@@ -0,0 +1,51 @@
+use alloc::{vec, vec::Vec};
+
+/// Number of bits used to store a register value.
+const VALUE_BITS: usize = 6;
+
+const VALUE_MASK: u32 = (1 << VALUE_BITS) - 1;
+
+/// Dense register values, packed on 6 bits each.
+///
+/// Registers are grouped by 4, and each group is stored in 3 little-endian bytes.
+#[derive(Clone, Debug)]
+pub struct PackedValues {
+    bytes: Vec<u8>,
+}
+
+impl PackedValues {
+    /// Creates `len` registers set to zero, `len` being a multiple of 4.
+    pub fn with_len(len: usize) -> Self {
+        debug_assert_eq!(len % 4, 0);
+        Self {
+            bytes: vec![0; len * VALUE_BITS / 8],
+        }
+    }
+
+    /// Returns the 3 bytes holding a register, and the offset of its value within them.
+    #[inline]
+    fn locate(index: usize) -> (usize, usize) {
+        (3 * (index / 4), VALUE_BITS * (index % 4))
+    }
+
+    #[inline]
+    fn word(&self, start: usize) -> u32 {
+        let bytes = &self.bytes[start..start + 3];
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0])
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> u8 {
+        let (start, shift) = Self::locate(index);
+        ((self.word(start) >> shift) & VALUE_MASK) as u8
+    }
+
+    /// Sets the value of a register, which must fit on 6 bits.
+    #[inline]
+    pub fn set(&mut self, index: usize, value: u8) {
+        debug_assert!(u32::from(value) <= VALUE_MASK);
+        let (start, shift) = Self::locate(index);
+        let word = (self.word(start) & !(VALUE_MASK << shift)) | (u32::from(value) << shift);
+        self.bytes[start..start + 3].copy_from_slice(&word.to_le_bytes()[..3]);
+    }
+}
@@ -0,0 +1,157 @@
+use core::hash::{BuildHasher, Hash};
+
+use rayon::{
+    iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator},
+    slice::ParallelSlice,
+};
+
+use crate::{
+    error::TryMergeError,
+    hyperloglog::{check_merge_conds, split_hash, HyperLogLog},
+    identity::HasherIdentity,
+    storage::RegisterStorage,
+};
+
+/// Minimum number of hyperloglogs merged by a single thread.
+const MERGE_CHUNK_SIZE: usize = 4;
+
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
+where
+    T: ?Sized,
+    R: RegisterStorage + Send,
+    S: Sync,
+{
+    /// Fills registers with the given precision from a parallel iterator.
+    ///
+    /// Each thread fills its own registers, which are then merged in a tree.
+    fn par_registers<I, Q>(&self, par_iter: I) -> Option<R>
+    where
+        I: IntoParallelIterator<Item = Q>,
+        Q: Hash,
+        S: BuildHasher,
+    {
+        let precision = self.precision();
+        let hash_builder = self.hasher();
+        par_iter
+            .into_par_iter()
+            .fold(
+                || R::with_precision(precision),
+                |mut registers, value| {
+                    let (index, rho) = split_hash(precision, hash_builder.hash_one(value));
+                    registers.update(index, rho);
+                    registers
+                },
+            )
+            .reduce_with(|mut lhs, rhs| {
+                lhs.merge_from_unchecked(&rhs);
+                lhs
+            })
+    }
+}
+
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
+where
+    T: ?Sized + Sync,
+    R: RegisterStorage + Clone + Send + Sync,
+    S: HasherIdentity + Sync,
+    E: Sync,
+{
+    /// Merges the hyperloglogs `rhs` into `self`, with several threads.
+    ///
+    /// Hyperloglogs are merged in a tree, each thread merging a subset of them before
+    /// merging with the others. This deactivates the HIP estimator, if any, unless `rhs`
+    /// is empty.
+    ///
+    /// Nothing is merged if the precision or the hasher of any hyperloglog is not the same
+    /// as in `self`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::HyperLogLog;
+    ///
+    /// let hlls: Vec<_> = (0..10)
+    ///     .map(|i| (i * 10_000..(i + 1) * 10_000).collect::<HyperLogLog<i32>>())
+    ///     .collect();
+    /// let mut hll = HyperLogLog::<i32>::new();
+    /// hll.try_par_merge_from(&hlls).unwrap();
+    /// assert!((50_000..150_000).contains(&hll.len()));
+    /// ```
+    pub fn try_par_merge_from(&mut self, rhs: &[Self]) -> Result<(), TryMergeError> {
+        for hll in rhs {
+            check_merge_conds(self, hll)?;
+        }
+        let merged = rhs
+            .par_chunks(MERGE_CHUNK_SIZE)
+            .map(|chunk| {
+                let mut registers = chunk[0].registers().clone();
+                for hll in &chunk[1..] {
+                    registers.merge_from_unchecked(hll.registers());
+                }
+                registers
+            })
+            .reduce_with(|mut lhs, rhs| {
+                lhs.merge_from_unchecked(&rhs);
+                lhs
+            });
+        if let Some(registers) = merged {
+            self.merge_registers_unchecked(&registers);
+        }
+        Ok(())
+    }
+}
+
+impl<T, S, E, R> ParallelExtend<T> for HyperLogLog<T, S, E, R>
+where
+    T: Hash + Send,
+    S: BuildHasher + Sync,
+    R: RegisterStorage + Send,
+{
+    /// Adds values to the hyperloglog, with several threads.
+    ///
+    /// This deactivates the HIP estimator, if any.
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = T>,
+    {
+        if let Some(registers) = self.par_registers(par_iter) {
+            self.merge_registers_unchecked(&registers);
+        }
+    }
+}
+
+impl<'a, T, S, E, R> ParallelExtend<&'a T> for HyperLogLog<T, S, E, R>
+where
+    T: 'a + Hash + Sync + ?Sized,
+    S: BuildHasher + Sync,
+    R: RegisterStorage + Send,
+{
+    /// Adds values to the hyperloglog, with several threads.
+    ///
+    /// This deactivates the HIP estimator, if any.
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = &'a T>,
+    {
+        if let Some(registers) = self.par_registers(par_iter) {
+            self.merge_registers_unchecked(&registers);
+        }
+    }
+}
+
+impl<T, S, E, R> FromParallelIterator<T> for HyperLogLog<T, S, E, R>
+where
+    T: Hash + Send,
+    S: BuildHasher + Default + Sync,
+    E: Default,
+    R: RegisterStorage + Default + Send,
+{
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = T>,
+    {
+        let mut hll = HyperLogLog::default();
+        hll.par_extend(par_iter);
+        hll
+    }
+}
@@ -1,4 +1,4 @@
-use std::fmt::{self, Display};
+use core::fmt::{self, Display};
 
 use crate::error::TryFromIntError;
 
@@ -18,7 +18,7 @@ use crate::error::TryFromIntError;
 /// let hll = HyperLogLog::<i32>::with_precision(Precision::P12);
 /// ```
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum Precision {
     /// 4-bit precision.
     P4 = 4,
@@ -37,6 +37,7 @@ pub enum Precision {
     /// 11-bit precision.
     P11 = 11,
     /// 12-bit precision (default).
+    #[default]
     P12 = 12,
     /// 13-bit precision.
     P13 = 13,
@@ -179,12 +180,6 @@ impl TryFrom<u8> for Precision {
     }
 }
 
-impl Default for Precision {
-    fn default() -> Self {
-        Self::P12
-    }
-}
-
 impl Display for Precision {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         self.get().fmt(f)
@@ -1,70 +1,419 @@
-use std::cmp::max;
+use alloc::{vec, vec::Vec};
+use core::iter::Peekable;
 
-use crate::precision::Precision;
+use crate::{
+    compact::CompactValues,
+    histogram::{Histogram, NUM_VALUES},
+    hyperloglog::{split_hash, BATCH_SIZE},
+    packed::PackedValues,
+    precision::Precision,
+    simd,
+    sparse::{decode_index, decode_value, SortedEntries, SparseValues},
+    storage::{fold_into, RegisterStorage},
+};
 
+/// Layout of the registers of a hyperloglog, once they no longer fit in the sparse
+/// representation.
+///
+/// # Examples
+///
+/// ```
+/// use hyperloglog::{HyperLogLog, RegisterLayout};
+///
+/// let mut hll = HyperLogLog::<i32>::new().with_layout(RegisterLayout::Packed);
+/// for i in 0..100_000 {
+///     hll.insert(&i);
+/// }
+/// assert!((50_000..150_000).contains(&hll.len()));
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum RegisterLayout {
+    /// One byte per register.
+    #[default]
+    Byte,
+    /// Six bits per register, which takes 25% less memory than [`Byte`](RegisterLayout::Byte),
+    /// at the cost of slightly slower updates.
+    Packed,
+    /// Four bits per register, which takes half the memory of
+    /// [`Byte`](RegisterLayout::Byte).
+    ///
+    /// Registers store the difference to the smallest register value, and the rare values
+    /// that do not fit are stored apart. Updates are slower, especially those raising the
+    /// smallest register value, which shift all other registers.
+    Compact,
+}
+
+impl RegisterLayout {
+    /// Returns the number of bytes taken by `len` registers.
+    #[inline]
+    fn dense_size(self, len: usize) -> usize {
+        match self {
+            RegisterLayout::Byte => len,
+            RegisterLayout::Packed => len * 3 / 4,
+            RegisterLayout::Compact => len / 2,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Values {
+    /// Encoded `(index, value)` pairs of the non-zero registers.
+    Sparse(SparseValues),
+    /// One value per register.
+    Dense(Vec<u8>),
+    /// One value per register, packed on 6 bits.
+    Packed(PackedValues),
+    /// One value per register, on 4 bits relative to the smallest value.
+    Compact(CompactValues),
+}
+
+impl Values {
+    /// Creates `len` dense registers set to zero.
+    fn dense(layout: RegisterLayout, len: usize) -> Self {
+        match layout {
+            RegisterLayout::Byte => Values::Dense(vec![0; len]),
+            RegisterLayout::Packed => Values::Packed(PackedValues::with_len(len)),
+            RegisterLayout::Compact => Values::Compact(CompactValues::with_len(len)),
+        }
+    }
+
+    /// Sets the value of a dense register, which must be zero.
+    #[inline]
+    fn set_dense(&mut self, index: usize, value: u8) {
+        match self {
+            Values::Sparse(_) => unreachable!("registers are sparse"),
+            Values::Dense(values) => values[index] = value,
+            Values::Packed(values) => values.set(index, value),
+            Values::Compact(values) => values.raise(index, value),
+        }
+    }
+}
+
+/// The default register storage of a [`HyperLogLog`](crate::HyperLogLog).
+///
+/// Registers start in a sparse representation that only stores the non-zero registers,
+/// and switch to a dense array of `2^p` registers, in the given [`RegisterLayout`], once
+/// that becomes more compact.
+///
+/// The histogram of register values is kept up to date on every change, so that estimating
+/// the number of elements does not depend on the number of registers.
 #[derive(Clone, Debug)]
 pub struct Registers {
     precision: Precision,
-    values: Vec<u8>,
+    layout: RegisterLayout,
+    values: Values,
+    counts: [u32; NUM_VALUES],
 }
 
 impl Registers {
-    pub fn with_precision(precision: Precision) -> Self {
-        let num_registers = 1 << precision.get();
-        Self {
-            precision,
-            values: vec![0; num_registers],
+    /// Returns the layout of the registers.
+    #[inline]
+    pub fn layout(&self) -> RegisterLayout {
+        self.layout
+    }
+
+    /// Changes the layout of the registers, converting them if they are dense.
+    pub fn set_layout(&mut self, layout: RegisterLayout) {
+        if layout == self.layout {
+            return;
+        }
+        self.layout = layout;
+        match &self.values {
+            Values::Sparse(values) => {
+                if values.len() > self.sparse_capacity() {
+                    self.densify();
+                }
+            }
+            Values::Dense(_) | Values::Packed(_) | Values::Compact(_) => {
+                let mut values = Values::dense(layout, self.len());
+                for (index, value) in self.iter().enumerate() {
+                    values.set_dense(index, value);
+                }
+                self.values = values;
+            }
+        }
+    }
+
+    /// Maximum number of sparse entries, above which the dense representation
+    /// takes less memory.
+    #[inline]
+    fn sparse_capacity(&self) -> usize {
+        self.layout.dense_size(self.len()) / core::mem::size_of::<u32>()
+    }
+
+    fn densify(&mut self) {
+        if let Values::Sparse(sparse) = &self.values {
+            let mut values = Values::dense(self.layout, self.len());
+            for entry in sparse.entries() {
+                values.set_dense(decode_index(entry), decode_value(entry));
+            }
+            self.values = values;
         }
     }
+
+    /// Accounts for a register raised from `previous` to `value` in the histogram.
+    #[inline]
+    fn count_update(&mut self, previous: u8, value: u8) {
+        self.counts[previous as usize] -= 1;
+        self.counts[value as usize] += 1;
+    }
 }
 
-impl Registers {
+impl Default for Registers {
+    /// Creates registers with the default precision.
     #[inline]
-    pub fn precision(&self) -> Precision {
-        self.precision
+    fn default() -> Self {
+        Self::with_precision(Precision::default())
+    }
+}
+
+impl RegisterStorage for Registers {
+    fn with_precision(precision: Precision) -> Self {
+        let mut registers = Self {
+            precision,
+            layout: RegisterLayout::default(),
+            values: Values::Sparse(SparseValues::default()),
+            counts: [0; NUM_VALUES],
+        };
+        registers.counts[0] = registers.len() as u32;
+        registers
     }
 
     #[inline]
-    pub fn len(&self) -> usize {
-        self.values.len()
+    fn precision(&self) -> Precision {
+        self.precision
     }
 
-    pub fn update(&mut self, index: usize, value: u8) {
-        let current_p = self.values.get_mut(index).unwrap();
-        if *current_p < value {
-            *current_p = value;
+    fn get(&self, index: usize) -> u8 {
+        match &self.values {
+            Values::Sparse(values) => {
+                assert!(index < self.len());
+                values.get(index)
+            }
+            Values::Dense(values) => values[index],
+            Values::Packed(values) => values.get(index),
+            Values::Compact(values) => values.get(index),
         }
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
-        self.values.iter().copied()
+    fn update(&mut self, index: usize, value: u8) -> u8 {
+        let len = self.len();
+        let sparse_capacity = self.sparse_capacity();
+        let previous = match &mut self.values {
+            Values::Sparse(values) => {
+                assert!(index < len);
+                let previous = values.update(index, value);
+                if values.len() > sparse_capacity {
+                    self.densify();
+                }
+                previous
+            }
+            Values::Dense(values) => {
+                let current_p = values.get_mut(index).unwrap();
+                let previous = *current_p;
+                if previous < value {
+                    *current_p = value;
+                }
+                previous
+            }
+            Values::Packed(values) => {
+                let previous = values.get(index);
+                if previous < value {
+                    values.set(index, value);
+                }
+                previous
+            }
+            Values::Compact(values) => {
+                let previous = values.get(index);
+                if previous < value {
+                    values.raise(index, value);
+                }
+                previous
+            }
+        };
+        if previous < value {
+            self.count_update(previous, value);
+        }
+        previous
     }
 
-    pub fn clear(&mut self) {
-        for value in &mut self.values {
-            *value = 0;
+    /// Updates registers for a batch of hash values.
+    ///
+    /// Dense registers are updated by chunks of hashes: all hashes of a chunk are split
+    /// first, prefetching the registers they designate, and registers are then raised
+    /// without bounds checks.
+    fn update_hashes(&mut self, mut hashes: &[u64]) {
+        let precision = self.precision;
+        // Sparse registers become dense as they fill up, possibly within the batch.
+        while let (Values::Sparse(_), Some((h, rest))) = (&self.values, hashes.split_first()) {
+            let (index, value) = split_hash(precision, *h);
+            self.update(index, value);
+            hashes = rest;
+        }
+        let Values::Dense(values) = &mut self.values else {
+            for h in hashes {
+                let (index, value) = split_hash(precision, *h);
+                self.update(index, value);
+            }
+            return;
+        };
+        debug_assert_eq!(values.len(), 1 << precision.get());
+        let mut splits = [(0, 0); BATCH_SIZE];
+        for chunk in hashes.chunks(BATCH_SIZE) {
+            let splits = &mut splits[..chunk.len()];
+            for (split, h) in splits.iter_mut().zip(chunk) {
+                *split = split_hash(precision, *h);
+                simd::prefetch(values.as_ptr().wrapping_add(split.0));
+            }
+            for &(index, value) in splits.iter() {
+                // SAFETY: indices are the `p` lower bits of hashes, and dense registers hold
+                // `2^p` values.
+                let current_p = unsafe { values.get_unchecked_mut(index) };
+                let previous = *current_p;
+                if previous < value {
+                    *current_p = value;
+                    self.counts[previous as usize] -= 1;
+                    self.counts[value as usize] += 1;
+                }
+            }
         }
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.values.iter().all(|value| *value == 0)
+    #[inline]
+    fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        let sparse = match &self.values {
+            Values::Sparse(values) => Some(values.sorted_entries().peekable()),
+            _ => None,
+        };
+        Iter {
+            registers: self,
+            index: 0,
+            sparse,
+        }
     }
 
-    pub fn merge_from_unchecked(&mut self, rhs: &Self) {
-        for (self_value_p, rhs_value_p) in self.values.iter_mut().zip(rhs.values.iter()) {
-            *self_value_p = max(*self_value_p, *rhs_value_p);
+    fn max_into(&self, start: usize, values: &mut [u8]) {
+        let len = self.len();
+        match &self.values {
+            Values::Sparse(sparse) => sparse.max_into(start, values),
+            Values::Dense(dense) => {
+                simd::max_assign(values, dense.get(start..).unwrap_or_default());
+            }
+            Values::Packed(packed) => {
+                for (index, value) in (start..len).zip(values) {
+                    *value = (*value).max(packed.get(index));
+                }
+            }
+            Values::Compact(compact) => {
+                for (index, value) in (start..len).zip(values) {
+                    *value = (*value).max(compact.get(index));
+                }
+            }
         }
     }
 
-    pub fn merge_unchecked(&self, rhs: &Self) -> Self {
-        Self {
-            precision: self.precision,
-            values: self
-                .values
-                .iter()
-                .zip(rhs.values.iter())
-                .map(|(self_value_p, other_value_p)| max(*self_value_p, *other_value_p))
-                .collect(),
+    #[inline]
+    fn histogram(&self) -> Histogram {
+        Histogram::new(self.precision, self.counts)
+    }
+
+    fn clear(&mut self) {
+        self.values = Values::Sparse(SparseValues::default());
+        self.counts = [0; NUM_VALUES];
+        self.counts[0] = self.len() as u32;
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.counts[0] as usize == self.len()
+    }
+
+    /// Folds registers to a lower precision, keeping their layout.
+    fn reduce_precision(&self, precision: Precision) -> Self {
+        let mut registers = Self::with_precision(precision);
+        registers.set_layout(self.layout);
+        fold_into(self, &mut registers);
+        registers
+    }
+
+    /// Merges `rhs` into `self`, whatever their layouts, ignoring registers of `rhs` beyond
+    /// the length of `self`.
+    fn merge_from_unchecked(&mut self, rhs: &Self) {
+        let len = self.len();
+        let sparse_capacity = self.sparse_capacity();
+        match (&mut self.values, &rhs.values) {
+            (Values::Sparse(self_values), Values::Sparse(rhs_values)) if rhs.len() <= len => {
+                self_values.merge_from(rhs_values);
+                let mut counts = [0; NUM_VALUES];
+                counts[0] = (len - self_values.len()) as u32;
+                for entry in self_values.entries() {
+                    counts[decode_value(entry) as usize] += 1;
+                }
+                self.counts = counts;
+                if self_values.len() > sparse_capacity {
+                    self.densify();
+                }
+            }
+            (Values::Sparse(_), _) => {
+                self.densify();
+                self.merge_from_unchecked(rhs);
+            }
+            (Values::Dense(self_values), Values::Dense(rhs_values)) => {
+                simd::max_assign_counting(self_values, rhs_values, &mut self.counts);
+            }
+            (_, Values::Sparse(rhs_values)) => {
+                for entry in rhs_values.entries() {
+                    if decode_index(entry) < len {
+                        self.update(decode_index(entry), decode_value(entry));
+                    }
+                }
+            }
+            (_, _) => {
+                for (index, value) in rhs.iter().take(len).enumerate() {
+                    if value > 0 {
+                        self.update(index, value);
+                    }
+                }
+            }
         }
     }
 }
+
+/// Iterator over the values of registers, returned by [`Registers::iter`].
+struct Iter<'a> {
+    registers: &'a Registers,
+    index: usize,
+    /// Sorted entries of sparse registers.
+    sparse: Option<Peekable<SortedEntries<'a>>>,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        if self.index == self.registers.len() {
+            return None;
+        }
+        let value = match &self.registers.values {
+            Values::Sparse(_) => {
+                let entries = self.sparse.as_mut().unwrap();
+                entries
+                    .next_if(|entry| decode_index(*entry) == self.index)
+                    .map_or(0, decode_value)
+            }
+            Values::Dense(values) => values[self.index],
+            Values::Packed(values) => values.get(self.index),
+            Values::Compact(values) => values.get(self.index),
+        };
+        self.index += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.registers.len() - self.index;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
@@ -0,0 +1,121 @@
+use alloc::vec::Vec;
+
+use crate::{
+    error::{FromBytesError, FromBytesErrorKind},
+    hyperloglog::HyperLogLog,
+    identity::{HasherId, HasherIdentity},
+    precision::Precision,
+    storage::RegisterStorage,
+    view::check_registers,
+};
+
+/// Leading bytes of serialized hyperloglogs.
+const MAGIC: [u8; 3] = *b"HLL";
+
+/// Version of the serialization format.
+const VERSION: u8 = 1;
+
+/// Length of the header: magic, version, precision, hasher algorithm and fingerprint.
+const HEADER_LEN: usize = 17;
+
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
+where
+    T: ?Sized,
+    S: HasherIdentity,
+    R: RegisterStorage,
+{
+    /// Serializes the hyperloglog into bytes.
+    ///
+    /// The bytes are a 17-byte header followed by the `2^p` registers, one byte each.
+    /// The header is made of the magic bytes `HLL`, the format version `1`, the precision,
+    /// then the [identity](HasherIdentity) of the hasher: its algorithm as a little-endian
+    /// `u32` and its fingerprint as a little-endian `u64`.
+    ///
+    /// Only registers are serialized: the estimator and the HIP estimate are not.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{HyperLogLog, Precision};
+    ///
+    /// let mut hll = HyperLogLog::<i32>::with_precision(Precision::P8);
+    /// hll.insert(&1);
+    /// let bytes = hll.to_bytes();
+    /// assert_eq!(bytes.len(), 17 + 256);
+    /// assert_eq!(&bytes[..5], b"HLL\x01\x08");
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let hasher_id = self.hasher().hasher_id();
+        let mut bytes = Vec::with_capacity(HEADER_LEN + self.registers().len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(VERSION);
+        bytes.push(self.precision().get());
+        bytes.extend_from_slice(&hasher_id.algorithm().to_le_bytes());
+        bytes.extend_from_slice(&hasher_id.fingerprint().to_le_bytes());
+        bytes.extend(self.registers().iter());
+        bytes
+    }
+}
+
+impl<T, S, E, R> HyperLogLog<T, S, E, R>
+where
+    T: ?Sized,
+    S: HasherIdentity,
+    E: Default,
+    R: RegisterStorage,
+{
+    /// Deserializes a hyperloglog from bytes written by [`to_bytes`](HyperLogLog::to_bytes),
+    /// given the hasher it was built with.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`FromBytesErrorKind::Hasher`] if the identity of `hasher` is not the one
+    /// written in the bytes, with [`FromBytesErrorKind::Precision`] if the register storage
+    /// does not [support](RegisterStorage::supports_precision) the precision written in the
+    /// bytes, and with [`FromBytesErrorKind::Invalid`] if the bytes are not a serialized
+    /// hyperloglog.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{BuildXxHash64, FromBytesErrorKind, HyperLogLog};
+    ///
+    /// let mut hll = HyperLogLog::<i32>::new();
+    /// hll.extend(0..1_000);
+    /// let bytes = hll.to_bytes();
+    ///
+    /// let copy = HyperLogLog::<i32>::from_bytes(&bytes, BuildXxHash64::default()).unwrap();
+    /// assert_eq!(copy.histogram(), hll.histogram());
+    ///
+    /// let error = HyperLogLog::<i32>::from_bytes(&bytes, BuildXxHash64::with_seed(1));
+    /// assert_eq!(error.unwrap_err().kind(), FromBytesErrorKind::Hasher);
+    /// ```
+    pub fn from_bytes(bytes: &[u8], hasher: S) -> Result<Self, FromBytesError> {
+        let invalid = FromBytesError {
+            kind: FromBytesErrorKind::Invalid,
+        };
+        if bytes.len() < HEADER_LEN || bytes[..3] != MAGIC || bytes[3] != VERSION {
+            return Err(invalid);
+        }
+        let precision = Precision::new(bytes[4]).ok_or(invalid)?;
+        let hasher_id = HasherId::new(
+            u32::from_le_bytes(bytes[5..9].try_into().unwrap()),
+            u64::from_le_bytes(bytes[9..HEADER_LEN].try_into().unwrap()),
+        );
+        if hasher.hasher_id() != hasher_id {
+            return Err(FromBytesError {
+                kind: FromBytesErrorKind::Hasher,
+            });
+        }
+        let values = &bytes[HEADER_LEN..];
+        check_registers(values, precision).map_err(|_| invalid)?;
+        if !R::supports_precision(precision) {
+            return Err(FromBytesError {
+                kind: FromBytesErrorKind::Precision,
+            });
+        }
+        let mut hll = Self::from_parts(R::with_precision(precision), hasher, E::default());
+        hll.merge_values_unchecked(values);
+        Ok(hll)
+    }
+}
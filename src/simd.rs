@@ -0,0 +1,285 @@
+//! Vectorized loops over dense registers, one byte per register.
+//!
+//! On `x86_64`, AVX2 is used when detected at runtime (or enabled at compile time without
+//! `std`), and SSE2 otherwise. Other architectures use the scalar fallbacks.
+
+// Histogram-maintaining merges are only used by the heap-allocated registers.
+#![cfg_attr(not(feature = "alloc"), allow(dead_code))]
+
+#[cfg(not(target_arch = "x86_64"))]
+pub use self::scalar::*;
+#[cfg(target_arch = "x86_64")]
+pub use self::x86::*;
+
+mod scalar {
+    use crate::histogram::NUM_VALUES;
+
+    /// Sets each register of `dst` to the maximum of itself and the register of `src`.
+    ///
+    /// If lengths differ, only the registers found in both slices are processed.
+    #[inline]
+    pub fn max_assign(dst: &mut [u8], src: &[u8]) {
+        for (dst_value_p, src_value) in dst.iter_mut().zip(src) {
+            *dst_value_p = (*dst_value_p).max(*src_value);
+        }
+    }
+
+    /// Sets each register of `dst` to the maximum of itself and the register of `src`, and
+    /// accounts for raised registers in the histogram `counts` of `dst`.
+    ///
+    /// If lengths differ, only the registers found in both slices are processed.
+    #[inline]
+    pub fn max_assign_counting(dst: &mut [u8], src: &[u8], counts: &mut [u32; NUM_VALUES]) {
+        for (dst_value_p, src_value) in dst.iter_mut().zip(src) {
+            let (previous, value) = (*dst_value_p, *src_value);
+            if previous < value {
+                *dst_value_p = value;
+                counts[previous as usize] -= 1;
+                counts[value as usize] += 1;
+            }
+        }
+    }
+
+    /// Returns the number of zero registers.
+    #[inline]
+    pub fn count_zeros(values: &[u8]) -> usize {
+        values.iter().filter(|value| **value == 0).count()
+    }
+
+    /// Returns the number of registers holding each value, which must be lower than
+    /// [`NUM_VALUES`].
+    #[cfg_attr(target_arch = "x86_64", allow(dead_code))]
+    #[inline]
+    pub fn histogram(values: &[u8]) -> [u32; NUM_VALUES] {
+        let mut counts = [0; NUM_VALUES];
+        add_histogram(values, &mut counts);
+        counts
+    }
+
+    #[inline]
+    pub(super) fn add_histogram(values: &[u8], counts: &mut [u32; NUM_VALUES]) {
+        for value in values {
+            counts[*value as usize] += 1;
+        }
+    }
+
+    /// Hints that the register at `p` is about to be updated.
+    #[cfg_attr(target_arch = "x86_64", allow(dead_code))]
+    #[inline]
+    pub fn prefetch(_p: *const u8) {}
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::scalar;
+    use crate::histogram::NUM_VALUES;
+
+    #[cfg(feature = "std")]
+    #[inline]
+    fn has_avx2() -> bool {
+        std::is_x86_feature_detected!("avx2")
+    }
+
+    #[cfg(not(feature = "std"))]
+    #[inline]
+    fn has_avx2() -> bool {
+        cfg!(target_feature = "avx2")
+    }
+
+    /// Sets each register of `dst` to the maximum of itself and the register of `src`.
+    ///
+    /// If lengths differ, only the registers found in both slices are processed.
+    pub fn max_assign(dst: &mut [u8], src: &[u8]) {
+        if has_avx2() {
+            // SAFETY: AVX2 is available.
+            unsafe { avx2::max_assign(dst, src) }
+        } else {
+            // SAFETY: SSE2 is always available on x86_64.
+            unsafe { sse2::max_assign(dst, src) }
+        }
+    }
+
+    /// Sets each register of `dst` to the maximum of itself and the register of `src`, and
+    /// accounts for raised registers in the histogram `counts` of `dst`.
+    ///
+    /// If lengths differ, only the registers found in both slices are processed.
+    pub fn max_assign_counting(dst: &mut [u8], src: &[u8], counts: &mut [u32; NUM_VALUES]) {
+        if has_avx2() {
+            // SAFETY: AVX2 is available.
+            unsafe { avx2::max_assign_counting(dst, src, counts) }
+        } else {
+            // SAFETY: SSE2 is always available on x86_64.
+            unsafe { sse2::max_assign_counting(dst, src, counts) }
+        }
+    }
+
+    /// Returns the number of zero registers.
+    pub fn count_zeros(values: &[u8]) -> usize {
+        if has_avx2() {
+            // SAFETY: AVX2 is available.
+            unsafe { avx2::count_zeros(values) }
+        } else {
+            // SAFETY: SSE2 is always available on x86_64.
+            unsafe { sse2::count_zeros(values) }
+        }
+    }
+
+    /// Returns the number of registers holding each value, which must be lower than
+    /// [`NUM_VALUES`].
+    pub fn histogram(values: &[u8]) -> [u32; NUM_VALUES] {
+        let mut counts = [0; NUM_VALUES];
+        if has_avx2() {
+            // SAFETY: AVX2 is available.
+            unsafe { avx2::add_histogram(values, &mut counts) }
+        } else {
+            // SAFETY: SSE2 is always available on x86_64.
+            unsafe { sse2::add_histogram(values, &mut counts) }
+        }
+        counts
+    }
+
+    /// Hints that the register at `p` is about to be updated.
+    #[inline]
+    pub fn prefetch(p: *const u8) {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+
+        // SAFETY: SSE is always available on x86_64, and prefetching does not dereference `p`.
+        unsafe { _mm_prefetch::<_MM_HINT_T0>(p as *const i8) }
+    }
+
+    /// Defines the loops for one vector width, given its intrinsics.
+    macro_rules! impl_loops {
+        (
+            $feature:literal,
+            $lanes:literal,
+            $vector:ident,
+            $load:ident,
+            $store:ident,
+            $max:ident,
+            $cmpeq:ident,
+            $movemask:ident,
+            $set1:ident $(,)?
+        ) => {
+            use core::arch::x86_64::{$cmpeq, $load, $max, $movemask, $set1, $store, $vector};
+
+            use super::scalar;
+            use crate::histogram::NUM_VALUES;
+
+            /// Comparison mask with all lanes set.
+            const ALL: u32 = u32::MAX >> (32 - $lanes);
+
+            /// Truncates both slices to the shorter length, so that their chunks and
+            /// remainders line up.
+            #[inline]
+            fn common<'a, 'b>(dst: &'a mut [u8], src: &'b [u8]) -> (&'a mut [u8], &'b [u8]) {
+                let len = dst.len().min(src.len());
+                (&mut dst[..len], &src[..len])
+            }
+
+            #[target_feature(enable = $feature)]
+            pub unsafe fn max_assign(dst: &mut [u8], src: &[u8]) {
+                let (dst, src) = common(dst, src);
+                let mut dst_chunks = dst.chunks_exact_mut($lanes);
+                let mut src_chunks = src.chunks_exact($lanes);
+                for (dst_chunk, src_chunk) in (&mut dst_chunks).zip(&mut src_chunks) {
+                    let dst_p = dst_chunk.as_mut_ptr() as *mut $vector;
+                    let a = $load(dst_p);
+                    let b = $load(src_chunk.as_ptr() as *const $vector);
+                    $store(dst_p, $max(a, b));
+                }
+                scalar::max_assign(dst_chunks.into_remainder(), src_chunks.remainder());
+            }
+
+            #[target_feature(enable = $feature)]
+            pub unsafe fn max_assign_counting(
+                dst: &mut [u8],
+                src: &[u8],
+                counts: &mut [u32; NUM_VALUES],
+            ) {
+                let (dst, src) = common(dst, src);
+                let mut dst_chunks = dst.chunks_exact_mut($lanes);
+                let mut src_chunks = src.chunks_exact($lanes);
+                for (dst_chunk, src_chunk) in (&mut dst_chunks).zip(&mut src_chunks) {
+                    let dst_p = dst_chunk.as_mut_ptr() as *mut $vector;
+                    let a = $load(dst_p);
+                    let b = $load(src_chunk.as_ptr() as *const $vector);
+                    let max = $max(a, b);
+                    // Chunks where the maximum is the destination are unchanged, which is
+                    // the common case when merging similar sketches.
+                    let mut raised = !($movemask($cmpeq(max, a)) as u32) & ALL;
+                    if raised != 0 {
+                        while raised != 0 {
+                            let lane = raised.trailing_zeros() as usize;
+                            counts[dst_chunk[lane] as usize] -= 1;
+                            counts[src_chunk[lane] as usize] += 1;
+                            raised &= raised - 1;
+                        }
+                        $store(dst_p, max);
+                    }
+                }
+                scalar::max_assign_counting(
+                    dst_chunks.into_remainder(),
+                    src_chunks.remainder(),
+                    counts,
+                );
+            }
+
+            #[target_feature(enable = $feature)]
+            pub unsafe fn count_zeros(values: &[u8]) -> usize {
+                let zero = $set1(0);
+                let mut chunks = values.chunks_exact($lanes);
+                let mut count = 0;
+                for chunk in &mut chunks {
+                    let v = $load(chunk.as_ptr() as *const $vector);
+                    count += ($movemask($cmpeq(v, zero)) as u32).count_ones() as usize;
+                }
+                count + scalar::count_zeros(chunks.remainder())
+            }
+
+            #[target_feature(enable = $feature)]
+            pub unsafe fn add_histogram(values: &[u8], counts: &mut [u32; NUM_VALUES]) {
+                let mut chunks = values.chunks_exact($lanes);
+                for chunk in &mut chunks {
+                    // Chunks of equal registers, such as zeros in sparsely filled sketches,
+                    // are counted at once.
+                    let v = $load(chunk.as_ptr() as *const $vector);
+                    let first = $set1(chunk[0] as i8);
+                    if $movemask($cmpeq(v, first)) as u32 == ALL {
+                        counts[chunk[0] as usize] += $lanes;
+                    } else {
+                        scalar::add_histogram(chunk, counts);
+                    }
+                }
+                scalar::add_histogram(chunks.remainder(), counts);
+            }
+        };
+    }
+
+    mod sse2 {
+        impl_loops!(
+            "sse2",
+            16,
+            __m128i,
+            _mm_loadu_si128,
+            _mm_storeu_si128,
+            _mm_max_epu8,
+            _mm_cmpeq_epi8,
+            _mm_movemask_epi8,
+            _mm_set1_epi8,
+        );
+    }
+
+    mod avx2 {
+        impl_loops!(
+            "avx2",
+            32,
+            __m256i,
+            _mm256_loadu_si256,
+            _mm256_storeu_si256,
+            _mm256_max_epu8,
+            _mm256_cmpeq_epi8,
+            _mm256_movemask_epi8,
+            _mm256_set1_epi8,
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use alloc::vec::Vec;
+use core::{cmp::Ordering, iter::Peekable, slice};
+
+/// Number of bits used to store a register value in an entry.
+const VALUE_BITS: u32 = 8;
+
+/// Number of new entries buffered before they are merged into the sorted entries.
+const BUFFER_LEN: usize = 512;
+
+#[inline]
+fn encode(index: usize, value: u8) -> u32 {
+    ((index as u32) << VALUE_BITS) | (value as u32)
+}
+
+#[inline]
+pub fn decode_index(entry: u32) -> usize {
+    (entry >> VALUE_BITS) as usize
+}
+
+#[inline]
+pub fn decode_value(entry: u32) -> u8 {
+    entry as u8
+}
+
+/// Non-zero register values, stored as encoded `(index, value)` entries.
+///
+/// Entries are kept sorted by index, so that registers are found by binary search. As
+/// inserting into a sorted list moves all the entries after it, new entries are first
+/// appended to a small unsorted buffer, which is sorted and merged into the list once
+/// full, as in HyperLogLog++. The indices of all entries are distinct.
+#[derive(Clone, Debug, Default)]
+pub struct SparseValues {
+    /// Entries sorted by index.
+    entries: Vec<u32>,
+    /// Entries not merged yet, whose indices are not in `entries`.
+    buffer: Vec<u32>,
+}
+
+impl SparseValues {
+    /// Returns the number of non-zero registers.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len() + self.buffer.len()
+    }
+
+    /// Returns the position of a register in the sorted entries, or in the buffer.
+    #[inline]
+    fn find(&self, index: usize) -> Result<usize, Option<usize>> {
+        self.entries
+            .binary_search_by_key(&index, |entry| decode_index(*entry))
+            .map_err(|_| position(&self.buffer, index))
+    }
+
+    pub fn get(&self, index: usize) -> u8 {
+        match self.find(index) {
+            Ok(pos) => decode_value(self.entries[pos]),
+            Err(Some(pos)) => decode_value(self.buffer[pos]),
+            Err(None) => 0,
+        }
+    }
+
+    /// Raises the value of a register to `value`, if lower, and returns its previous value.
+    pub fn update(&mut self, index: usize, value: u8) -> u8 {
+        let entry = match self.find(index) {
+            Ok(pos) => &mut self.entries[pos],
+            Err(Some(pos)) => &mut self.buffer[pos],
+            Err(None) => {
+                if value > 0 {
+                    self.buffer.push(encode(index, value));
+                    if self.buffer.len() == BUFFER_LEN {
+                        self.flush();
+                    }
+                }
+                return 0;
+            }
+        };
+        let previous = decode_value(*entry);
+        if previous < value {
+            *entry = encode(index, value);
+        }
+        previous
+    }
+
+    /// Merges the buffer into the sorted entries.
+    fn flush(&mut self) {
+        self.buffer.sort_unstable();
+        merge_sorted(&mut self.entries, &self.buffer);
+        self.buffer.clear();
+    }
+
+    /// Merges the registers `rhs` into `self`, keeping the largest value of each register.
+    pub fn merge_from(&mut self, rhs: &Self) {
+        self.flush();
+        merge_sorted(&mut self.entries, &rhs.entries);
+        let (buffer, len) = sorted_buffer(&rhs.buffer);
+        merge_sorted(&mut self.entries, &buffer[..len]);
+    }
+
+    /// Raises each of `values` to the value of the register at the same index plus
+    /// `start`, if lower.
+    pub fn max_into(&self, start: usize, values: &mut [u8]) {
+        let end = start.saturating_add(values.len());
+        let first = self
+            .entries
+            .partition_point(|entry| decode_index(*entry) < start);
+        let entries = self.entries[first..]
+            .iter()
+            .take_while(|entry| decode_index(**entry) < end);
+        let buffered = self
+            .buffer
+            .iter()
+            .filter(|entry| (start..end).contains(&decode_index(**entry)));
+        for entry in entries.chain(buffered) {
+            let value = &mut values[decode_index(*entry) - start];
+            *value = (*value).max(decode_value(*entry));
+        }
+    }
+
+    /// Returns an iterator over all entries, in no particular order.
+    #[inline]
+    pub fn entries(&self) -> impl Iterator<Item = u32> + '_ {
+        self.entries.iter().chain(&self.buffer).copied()
+    }
+
+    /// Returns an iterator over all entries, sorted by index.
+    pub fn sorted_entries(&self) -> SortedEntries<'_> {
+        let (buffer, buffer_len) = sorted_buffer(&self.buffer);
+        SortedEntries {
+            entries: self.entries.iter().peekable(),
+            buffer,
+            buffer_len,
+            buffer_pos: 0,
+        }
+    }
+}
+
+/// Returns the position of a register in unsorted entries.
+#[inline]
+fn position(entries: &[u32], index: usize) -> Option<usize> {
+    // Chunks are searched without early exits, which is vectorized.
+    const CHUNK_LEN: usize = 16;
+    let key = (index as u32) << VALUE_BITS;
+    let matches = |entry: &u32| (entry >> VALUE_BITS) << VALUE_BITS == key;
+    entries
+        .chunks(CHUNK_LEN)
+        .enumerate()
+        .find(|(_, chunk)| {
+            chunk
+                .iter()
+                .fold(false, |found, entry| found | matches(entry))
+        })
+        .map(|(i, chunk)| i * CHUNK_LEN + chunk.iter().position(matches).unwrap())
+}
+
+/// Returns a sorted copy of a buffer of entries, together with its length.
+#[inline]
+fn sorted_buffer(buffer: &[u32]) -> ([u32; BUFFER_LEN], usize) {
+    let mut sorted = [0; BUFFER_LEN];
+    sorted[..buffer.len()].copy_from_slice(buffer);
+    sorted[..buffer.len()].sort_unstable();
+    (sorted, buffer.len())
+}
+
+/// Merges sorted entries into sorted entries, keeping the largest value for each index.
+///
+/// Entries are merged from the back, into the space reserved at the end of `entries`, so
+/// that no entry is overwritten before it is merged.
+fn merge_sorted(entries: &mut Vec<u32>, other: &[u32]) {
+    let len = entries.len();
+    entries.resize(len + other.len(), 0);
+    let (mut i, mut j, mut k) = (len, other.len(), len + other.len());
+    while j > 0 {
+        let r = other[j - 1];
+        let entry = match i.checked_sub(1).map(|i| entries[i]) {
+            Some(l) => match decode_index(l).cmp(&decode_index(r)) {
+                Ordering::Greater => {
+                    i -= 1;
+                    l
+                }
+                Ordering::Less => {
+                    j -= 1;
+                    r
+                }
+                Ordering::Equal => {
+                    // Entries share the same index, so the largest one holds the largest
+                    // value.
+                    i -= 1;
+                    j -= 1;
+                    l.max(r)
+                }
+            },
+            None => {
+                j -= 1;
+                r
+            }
+        };
+        k -= 1;
+        entries[k] = entry;
+    }
+    // Entries of the same index were merged into one, leaving a gap.
+    if k > i {
+        entries.copy_within(k.., i);
+        entries.truncate(entries.len() - (k - i));
+    }
+}
+
+/// Iterator over the entries of sparse registers, sorted by index, returned by
+/// [`SparseValues::sorted_entries`].
+pub struct SortedEntries<'a> {
+    entries: Peekable<slice::Iter<'a, u32>>,
+    buffer: [u32; BUFFER_LEN],
+    buffer_len: usize,
+    buffer_pos: usize,
+}
+
+impl Iterator for SortedEntries<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        let buffered = self.buffer[..self.buffer_len].get(self.buffer_pos).copied();
+        match (self.entries.peek(), buffered) {
+            (Some(entry), Some(buffered)) if buffered < **entry => {
+                self.buffer_pos += 1;
+                Some(buffered)
+            }
+            (None, Some(buffered)) => {
+                self.buffer_pos += 1;
+                Some(buffered)
+            }
+            _ => self.entries.next().copied(),
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{
+    histogram::Histogram,
+    hyperloglog::{fold_value, split_hash},
+    precision::Precision,
+};
+
+/// Storage of the registers of a hyperloglog.
+///
+/// [`HyperLogLog`](crate::HyperLogLog) is generic over its register storage, which
+/// defaults to [`Registers`](crate::Registers). Estimation, merging and hashing are
+/// implemented once on top of this trait, so that other layouts only need to store
+/// register values.
+///
+/// Only [`with_precision`](RegisterStorage::with_precision),
+/// [`precision`](RegisterStorage::precision), [`get`](RegisterStorage::get),
+/// [`update`](RegisterStorage::update), [`iter`](RegisterStorage::iter) and
+/// [`clear`](RegisterStorage::clear) are required. The other methods have default
+/// implementations built on them, which storages may override with faster ones.
+/// Storages implementing [`Default`] may also be created by
+/// [`HyperLogLog::new`](crate::HyperLogLog::new).
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::hash_map::RandomState;
+///
+/// use hyperloglog::{EstimationStrategy, HyperLogLog, Precision, RegisterStorage};
+///
+/// #[derive(Clone, Debug)]
+/// struct ByteRegisters {
+///     precision: Precision,
+///     values: Vec<u8>,
+/// }
+///
+/// impl RegisterStorage for ByteRegisters {
+///     fn with_precision(precision: Precision) -> Self {
+///         let values = vec![0; 1 << precision.get()];
+///         Self { precision, values }
+///     }
+///
+///     fn precision(&self) -> Precision {
+///         self.precision
+///     }
+///
+///     fn get(&self, index: usize) -> u8 {
+///         self.values[index]
+///     }
+///
+///     fn update(&mut self, index: usize, value: u8) -> u8 {
+///         let previous = self.values[index];
+///         self.values[index] = previous.max(value);
+///         previous
+///     }
+///
+///     fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+///         self.values.iter().copied()
+///     }
+///
+///     fn clear(&mut self) {
+///         self.values.fill(0);
+///     }
+/// }
+///
+/// let mut hll =
+///     HyperLogLog::<i32, RandomState, EstimationStrategy, ByteRegisters>::with_precision(
+///         Precision::P12,
+///     );
+/// for i in 0..100_000 {
+///     hll.insert(&i);
+/// }
+/// assert!((50_000..150_000).contains(&hll.len()));
+/// ```
+pub trait RegisterStorage {
+    /// Creates `2^p` registers set to zero, `p` being the given precision.
+    ///
+    /// # Panics
+    ///
+    /// May panic if the storage does not [support](RegisterStorage::supports_precision)
+    /// the precision.
+    fn with_precision(precision: Precision) -> Self
+    where
+        Self: Sized;
+
+    /// Returns the precision of registers created without an explicit precision, which is
+    /// [`Precision::default`] by default.
+    #[inline]
+    fn default_precision() -> Precision
+    where
+        Self: Sized,
+    {
+        Precision::default()
+    }
+
+    /// Returns `true` if registers may be created with the given precision, which is the
+    /// case of all precisions by default.
+    #[inline]
+    fn supports_precision(precision: Precision) -> bool
+    where
+        Self: Sized,
+    {
+        let _ = precision;
+        true
+    }
+
+    /// Returns the precision of the registers.
+    fn precision(&self) -> Precision;
+
+    /// Returns the number of registers, that is, `2^p`.
+    #[inline]
+    fn len(&self) -> usize {
+        1 << self.precision().get()
+    }
+
+    /// Returns the value of a register.
+    ///
+    /// # Panics
+    ///
+    /// May panic if `index` is not lower than [`len`](RegisterStorage::len).
+    fn get(&self, index: usize) -> u8;
+
+    /// Raises the value of a register to `value`, if lower, and returns its previous value.
+    ///
+    /// # Panics
+    ///
+    /// May panic if `index` is not lower than [`len`](RegisterStorage::len).
+    fn update(&mut self, index: usize, value: u8) -> u8;
+
+    /// Updates the registers designated by a batch of hash values, as
+    /// [`HyperLogLog::insert_hash`](crate::HyperLogLog::insert_hash) would for each of them.
+    fn update_hashes(&mut self, hashes: &[u64]) {
+        let precision = self.precision();
+        for h in hashes {
+            let (index, value) = split_hash(precision, *h);
+            self.update(index, value);
+        }
+    }
+
+    /// Returns an iterator over the values of all registers, in index order.
+    fn iter(&self) -> impl Iterator<Item = u8> + '_;
+
+    /// Raises each of `values` to the value of the register at the same index plus
+    /// `start`, if lower. Values past the last register are left unchanged.
+    ///
+    /// This lets the union of several storages be computed by chunks, without building it.
+    fn max_into(&self, start: usize, values: &mut [u8]) {
+        for (index, value) in (start..self.len()).zip(values) {
+            *value = (*value).max(self.get(index));
+        }
+    }
+
+    /// Returns the histogram of register values.
+    fn histogram(&self) -> Histogram {
+        Histogram::from_values(self.precision(), self.iter())
+    }
+
+    /// Sets all registers to zero.
+    fn clear(&mut self);
+
+    /// Returns `true` if all registers are zero.
+    fn is_empty(&self) -> bool {
+        self.iter().all(|value| value == 0)
+    }
+
+    /// Returns the registers folded to a lower precision, as if hash values had been split
+    /// with that precision.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `precision` is higher than the precision of the registers, or if the
+    /// storage does not support it.
+    fn reduce_precision(&self, precision: Precision) -> Self
+    where
+        Self: Sized,
+    {
+        let mut registers = Self::with_precision(precision);
+        fold_into(self, &mut registers);
+        registers
+    }
+
+    /// Merges the registers `rhs` into `self`, keeping the largest value of each register,
+    /// without checking that precisions are the same.
+    ///
+    /// If precisions differ, only the registers found in both are merged.
+    fn merge_from_unchecked(&mut self, rhs: &Self)
+    where
+        Self: Sized,
+    {
+        for (index, value) in rhs.iter().take(self.len()).enumerate() {
+            if value > 0 {
+                self.update(index, value);
+            }
+        }
+    }
+}
+
+/// Folds registers into registers of a lower precision.
+///
+/// # Panics
+///
+/// Panics if the precision of `dst` is higher than the precision of `src`.
+pub(crate) fn fold_into<R>(src: &R, dst: &mut R)
+where
+    R: RegisterStorage,
+{
+    let (from, to) = (src.precision(), dst.precision());
+    assert!(to <= from, "precision must not be higher than {from:?}");
+    let mask = (1 << to.get()) - 1;
+    for (index, value) in src.iter().enumerate() {
+        if value > 0 {
+            dst.update(index & mask, fold_value(from, to, index, value));
+        }
+    }
+}
@@ -0,0 +1,526 @@
+use core::{
+    borrow::Borrow,
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash},
+    marker::PhantomData,
+};
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
+use crate::{
+    error::{TryFromSliceError, TryMergeError},
+    estimate::Estimate,
+    estimator::{EstimationStrategy, Estimator},
+    histogram::Histogram,
+    hyperloglog::{check_merge_params, split_hash, DefaultBuildHasher, HyperLogLog},
+    identity::HasherIdentity,
+    precision::Precision,
+    simd,
+    storage::RegisterStorage,
+    xxhash::hash_bytes,
+};
+
+/// Checks that a byte slice holds valid registers for the given precision.
+pub(crate) fn check_registers(
+    registers: &[u8],
+    precision: Precision,
+) -> Result<(), TryFromSliceError> {
+    let max_value = 65 - precision.get();
+    if registers.len() != 1 << precision.get() || registers.iter().any(|value| *value > max_value) {
+        return Err(TryFromSliceError(()));
+    }
+    Ok(())
+}
+
+#[inline]
+fn histogram(registers: &[u8], precision: Precision) -> Histogram {
+    Histogram::new(precision, simd::histogram(registers))
+}
+
+fn merge_into<T, S, E, R>(
+    registers: &[u8],
+    precision: Precision,
+    hasher: &S,
+    hll: &mut HyperLogLog<T, S, E, R>,
+) -> Result<(), TryMergeError>
+where
+    T: ?Sized,
+    S: HasherIdentity,
+    R: RegisterStorage,
+{
+    check_merge_params((hll.precision(), hll.hasher()), (precision, hasher))?;
+    hll.merge_values_unchecked(registers);
+    Ok(())
+}
+
+/// A read-only hyperloglog whose registers are borrowed from a byte slice, one byte per
+/// register.
+///
+/// The slice is used as is, without copying: this is handy to estimate or merge sketches
+/// stored in larger buffers.
+///
+/// # Examples
+///
+/// ```
+/// use hyperloglog::{HyperLogLog, HyperLogLogRef, Precision};
+///
+/// let mut bytes = vec![0; 1 << 12];
+/// bytes[42] = 3;
+/// let view = HyperLogLogRef::<i32>::new(&bytes, Precision::P12).unwrap();
+/// assert_eq!(view.len(), 1);
+///
+/// let mut hll = HyperLogLog::<i32>::with_precision(Precision::P12);
+/// hll.insert(&1);
+/// view.try_merge_into(&mut hll).unwrap();
+/// assert_eq!(hll.len(), 2);
+/// ```
+pub struct HyperLogLogRef<'a, T, S = DefaultBuildHasher, E = EstimationStrategy>
+where
+    T: ?Sized,
+{
+    registers: &'a [u8],
+    precision: Precision,
+    hash_builder: S,
+    estimator: E,
+    phantom: PhantomData<T>,
+}
+
+impl<'a, T, S, E> HyperLogLogRef<'a, T, S, E>
+where
+    T: ?Sized,
+    S: Default,
+    E: Default,
+{
+    /// Creates a view over the registers held by a byte slice.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the slice does not hold `2^p` registers, each at most `65 - p`.
+    #[inline]
+    pub fn new(registers: &'a [u8], precision: Precision) -> Result<Self, TryFromSliceError> {
+        Self::with_hasher(registers, precision, S::default())
+    }
+}
+
+impl<'a, T, S, E> HyperLogLogRef<'a, T, S, E>
+where
+    T: ?Sized,
+    E: Default,
+{
+    /// Creates a view over the registers held by a byte slice, with the given hasher.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the slice does not hold `2^p` registers, each at most `65 - p`.
+    pub fn with_hasher(
+        registers: &'a [u8],
+        precision: Precision,
+        hasher: S,
+    ) -> Result<Self, TryFromSliceError> {
+        check_registers(registers, precision)?;
+        Ok(Self {
+            registers,
+            precision,
+            hash_builder: hasher,
+            estimator: E::default(),
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<'a, T, S, E> HyperLogLogRef<'a, T, S, E>
+where
+    T: ?Sized,
+{
+    /// Returns the precision of the hyperloglog.
+    #[inline]
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    /// Returns a reference to the hyperloglog's [`BuildHasher`].
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    /// Returns a reference to the hyperloglog's [`Estimator`].
+    #[inline]
+    pub fn estimator(&self) -> &E {
+        &self.estimator
+    }
+
+    /// Returns the borrowed registers.
+    #[inline]
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.registers
+    }
+
+    /// Returns the histogram of register values.
+    #[inline]
+    pub fn histogram(&self) -> Histogram {
+        histogram(self.registers, self.precision)
+    }
+
+    /// Estimates the number of different elements with the given estimator, regardless of
+    /// the hyperloglog's own estimator.
+    #[inline]
+    pub fn estimate_with<F>(&self, estimator: &F) -> f64
+    where
+        F: Estimator + ?Sized,
+    {
+        estimator.estimate(&self.histogram())
+    }
+
+    /// Returns `true` if the hyperloglog contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        simd::count_zeros(self.registers) == self.registers.len()
+    }
+
+    /// Merges the registers of `self` into the hyperloglog `hll`.
+    ///
+    /// This deactivates the HIP estimator of `hll`, if any.
+    ///
+    /// # Errors
+    ///
+    /// Fails if precisions or hashers differ.
+    pub fn try_merge_into<F, R>(
+        &self,
+        hll: &mut HyperLogLog<T, S, F, R>,
+    ) -> Result<(), TryMergeError>
+    where
+        S: HasherIdentity,
+        R: RegisterStorage,
+    {
+        merge_into(self.registers, self.precision, &self.hash_builder, hll)
+    }
+}
+
+impl<'a, T, S, E> HyperLogLogRef<'a, T, S, E>
+where
+    T: ?Sized,
+    E: Estimator,
+{
+    /// Calculates the approximate number of different elements.
+    pub fn len(&self) -> usize {
+        self.estimate_with(&self.estimator).round() as usize
+    }
+
+    /// Estimates the number of different elements, together with its standard error and
+    /// the bounds of a confidence interval at the given confidence level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not strictly between 0 and 1.
+    pub fn estimate(&self, confidence: f64) -> Estimate {
+        Estimate::from_histogram(&self.estimator, &self.histogram(), confidence)
+    }
+}
+
+impl<'a, T, S, E> Clone for HyperLogLogRef<'a, T, S, E>
+where
+    T: ?Sized,
+    S: Clone,
+    E: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            registers: self.registers,
+            precision: self.precision,
+            hash_builder: self.hash_builder.clone(),
+            estimator: self.estimator.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, S, E> HyperLogLogRef<'a, T, S, E>
+where
+    T: ?Sized,
+    S: Debug,
+    E: Debug,
+{
+    fn fmt_as(&self, name: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(name)
+            .field("registers", &self.registers)
+            .field("precision", &self.precision)
+            .field("hash_builder", &self.hash_builder)
+            .field("estimator", &self.estimator)
+            .field("phantom", &self.phantom)
+            .finish()
+    }
+}
+
+impl<'a, T, S, E> Debug for HyperLogLogRef<'a, T, S, E>
+where
+    T: ?Sized,
+    S: Debug,
+    E: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_as("HyperLogLogRef", f)
+    }
+}
+
+/// A hyperloglog whose registers are mutably borrowed from a byte slice, one byte per
+/// register.
+///
+/// Insertions update the slice in place, without copying.
+///
+/// # Examples
+///
+/// ```
+/// use hyperloglog::{HyperLogLogMut, Precision};
+///
+/// let mut bytes = vec![0; 1 << 12];
+/// let mut hll = HyperLogLogMut::<i32>::new(&mut bytes, Precision::P12).unwrap();
+/// for i in 0..100_000 {
+///     hll.insert(&i);
+/// }
+/// assert!((50_000..150_000).contains(&hll.len()));
+/// assert!(bytes.iter().any(|value| *value > 0));
+/// ```
+pub struct HyperLogLogMut<'a, T, S = DefaultBuildHasher, E = EstimationStrategy>
+where
+    T: ?Sized,
+{
+    registers: &'a mut [u8],
+    precision: Precision,
+    hash_builder: S,
+    estimator: E,
+    phantom: PhantomData<T>,
+}
+
+impl<'a, T, S, E> HyperLogLogMut<'a, T, S, E>
+where
+    T: ?Sized,
+    S: Default,
+    E: Default,
+{
+    /// Creates a hyperloglog over the registers held by a byte slice.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the slice does not hold `2^p` registers, each at most `65 - p`.
+    #[inline]
+    pub fn new(registers: &'a mut [u8], precision: Precision) -> Result<Self, TryFromSliceError> {
+        Self::with_hasher(registers, precision, S::default())
+    }
+}
+
+impl<'a, T, S, E> HyperLogLogMut<'a, T, S, E>
+where
+    T: ?Sized,
+    E: Default,
+{
+    /// Creates a hyperloglog over the registers held by a byte slice, with the given hasher.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the slice does not hold `2^p` registers, each at most `65 - p`.
+    pub fn with_hasher(
+        registers: &'a mut [u8],
+        precision: Precision,
+        hasher: S,
+    ) -> Result<Self, TryFromSliceError> {
+        check_registers(registers, precision)?;
+        Ok(Self {
+            registers,
+            precision,
+            hash_builder: hasher,
+            estimator: E::default(),
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<'a, T, S, E> HyperLogLogMut<'a, T, S, E>
+where
+    T: ?Sized,
+{
+    /// Returns a read-only view over the same registers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hyperloglog::{HyperLogLogMut, Precision};
+    ///
+    /// let mut bytes = vec![0; 1 << 12];
+    /// let mut hll = HyperLogLogMut::<i32>::new(&mut bytes, Precision::P12).unwrap();
+    /// hll.insert(&1);
+    /// assert_eq!(hll.as_ref().len(), 1);
+    /// ```
+    #[inline]
+    pub fn as_ref(&self) -> HyperLogLogRef<'_, T, &S, &E> {
+        HyperLogLogRef {
+            registers: self.registers,
+            precision: self.precision,
+            hash_builder: &self.hash_builder,
+            estimator: &self.estimator,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the precision of the hyperloglog.
+    #[inline]
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    /// Returns a reference to the hyperloglog's [`BuildHasher`].
+    #[inline]
+    pub fn hasher(&self) -> &S {
+        &self.hash_builder
+    }
+
+    /// Returns a reference to the hyperloglog's [`Estimator`].
+    #[inline]
+    pub fn estimator(&self) -> &E {
+        &self.estimator
+    }
+
+    /// Returns the borrowed registers.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        self.registers
+    }
+
+    /// Adds raw bytes to the hyperloglog, regardless of its hasher.
+    ///
+    /// See [`HyperLogLog::insert_bytes`] for details.
+    #[inline]
+    pub fn insert_bytes(&mut self, bytes: &[u8]) {
+        self.insert_hash(hash_bytes(bytes));
+    }
+
+    /// Adds a hash value to the hyperloglog.
+    ///
+    /// See [`HyperLogLog::insert_hash`] for details.
+    #[inline]
+    pub fn insert_hash(&mut self, h: u64) {
+        let (j, rho) = split_hash(self.precision, h);
+        let current_p = &mut self.registers[j];
+        if *current_p < rho {
+            *current_p = rho;
+        }
+    }
+
+    /// Returns the histogram of register values.
+    #[inline]
+    pub fn histogram(&self) -> Histogram {
+        self.as_ref().histogram()
+    }
+
+    /// Estimates the number of different elements with the given estimator, regardless of
+    /// the hyperloglog's own estimator.
+    #[inline]
+    pub fn estimate_with<F>(&self, estimator: &F) -> f64
+    where
+        F: Estimator + ?Sized,
+    {
+        self.as_ref().estimate_with(estimator)
+    }
+
+    /// Clears the hyperloglog, setting all borrowed registers to zero.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.registers.fill(0);
+    }
+
+    /// Returns `true` if the hyperloglog contains no elements.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.as_ref().is_empty()
+    }
+
+    /// Merges the registers of `self` into the hyperloglog `hll`.
+    ///
+    /// This deactivates the HIP estimator of `hll`, if any.
+    ///
+    /// # Errors
+    ///
+    /// Fails if precisions or hashers differ.
+    pub fn try_merge_into<F, R>(
+        &self,
+        hll: &mut HyperLogLog<T, S, F, R>,
+    ) -> Result<(), TryMergeError>
+    where
+        S: HasherIdentity,
+        R: RegisterStorage,
+    {
+        merge_into(self.registers, self.precision, &self.hash_builder, hll)
+    }
+}
+
+impl<'a, T, S, E> HyperLogLogMut<'a, T, S, E>
+where
+    T: ?Sized,
+    E: Estimator,
+{
+    /// Calculates the approximate number of different elements.
+    pub fn len(&self) -> usize {
+        self.as_ref().len()
+    }
+
+    /// Estimates the number of different elements, together with its standard error and
+    /// the bounds of a confidence interval at the given confidence level.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `confidence` is not strictly between 0 and 1.
+    pub fn estimate(&self, confidence: f64) -> Estimate {
+        self.as_ref().estimate(confidence)
+    }
+}
+
+impl<'a, T, S, E> HyperLogLogMut<'a, T, S, E>
+where
+    T: ?Sized,
+    S: BuildHasher,
+{
+    /// Adds a value to the hyperloglog.
+    pub fn insert<Q>(&mut self, value: &Q)
+    where
+        T: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        let h = self.hash_builder.hash_one(value);
+        self.insert_hash(h);
+    }
+}
+
+impl<'a, T, S, E> Debug for HyperLogLogMut<'a, T, S, E>
+where
+    T: ?Sized,
+    S: Debug,
+    E: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_ref().fmt_as("HyperLogLogMut", f)
+    }
+}
+
+impl<'a, T, S, E> Extend<&'a T> for HyperLogLogMut<'_, T, S, E>
+where
+    T: 'a + Hash + ?Sized,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<T, S, E> Extend<T> for HyperLogLogMut<'_, T, S, E>
+where
+    T: Hash,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(&value);
+        }
+    }
+}
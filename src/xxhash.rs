@@ -0,0 +1,271 @@
+use core::hash::{BuildHasher, Hasher};
+
+const PRIME_1: u64 = 0x9e37_79b1_85eb_ca87;
+const PRIME_2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const PRIME_3: u64 = 0x1656_67b1_9e37_79f9;
+const PRIME_4: u64 = 0x85eb_ca77_c2b2_ae63;
+const PRIME_5: u64 = 0x27d4_eb2f_1656_67c5;
+
+/// Number of bytes consumed at once by the accumulators.
+const STRIPE_LEN: usize = 32;
+
+#[inline]
+fn round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(PRIME_2))
+        .rotate_left(31)
+        .wrapping_mul(PRIME_1)
+}
+
+#[inline]
+fn merge_round(acc: u64, value: u64) -> u64 {
+    (acc ^ round(0, value))
+        .wrapping_mul(PRIME_1)
+        .wrapping_add(PRIME_4)
+}
+
+#[inline]
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+#[inline]
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+/// The [xxHash64](https://github.com/Cyan4973/xxHash/blob/dev/doc/xxhash_spec.md) hash
+/// function.
+///
+/// Unlike the hashers of the standard library, whose algorithm may change between Rust
+/// releases, this algorithm is frozen: the same bytes and seed always hash to the same
+/// value, which matches the reference implementation of xxHash64.
+///
+/// Integers are written in little-endian order, and `usize` and `isize` as 64-bit
+/// integers, so that hash values do not depend on the platform either.
+///
+/// Values hashed through their [`Hash`](core::hash::Hash) implementation are only as
+/// stable as the bytes that implementation writes. Fixed-width integers write their value
+/// alone, but other types, such as strings and slices, write extra bytes chosen by the
+/// standard library, which may change between Rust releases.
+///
+/// # Examples
+///
+/// ```
+/// use std::hash::Hasher;
+///
+/// use hyperloglog::XxHash64;
+///
+/// let mut hasher = XxHash64::with_seed(0);
+/// hasher.write(b"abc");
+/// assert_eq!(hasher.finish(), 0x44bc_2cf5_ad77_0999);
+/// ```
+#[derive(Clone, Debug)]
+pub struct XxHash64 {
+    seed: u64,
+    accs: [u64; 4],
+    /// Bytes not consumed by the accumulators yet.
+    buffer: [u8; STRIPE_LEN],
+    buffer_len: usize,
+    total_len: u64,
+}
+
+impl XxHash64 {
+    /// Creates a hasher with the given seed.
+    #[inline]
+    #[must_use]
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            accs: [
+                seed.wrapping_add(PRIME_1).wrapping_add(PRIME_2),
+                seed.wrapping_add(PRIME_2),
+                seed,
+                seed.wrapping_sub(PRIME_1),
+            ],
+            buffer: [0; STRIPE_LEN],
+            buffer_len: 0,
+            total_len: 0,
+        }
+    }
+
+    #[inline]
+    fn consume(accs: &mut [u64; 4], stripe: &[u8]) {
+        for (i, acc) in accs.iter_mut().enumerate() {
+            *acc = round(*acc, read_u64(&stripe[8 * i..]));
+        }
+    }
+}
+
+impl Default for XxHash64 {
+    /// Creates a hasher with a zero seed.
+    #[inline]
+    fn default() -> Self {
+        Self::with_seed(0)
+    }
+}
+
+impl Hasher for XxHash64 {
+    #[inline]
+    fn write(&mut self, mut bytes: &[u8]) {
+        self.total_len += bytes.len() as u64;
+        // Most values are hashed from a few small writes, which only fill the buffer.
+        if self.buffer_len + bytes.len() < STRIPE_LEN {
+            self.buffer[self.buffer_len..self.buffer_len + bytes.len()].copy_from_slice(bytes);
+            self.buffer_len += bytes.len();
+            return;
+        }
+        if self.buffer_len > 0 {
+            let len = bytes.len().min(STRIPE_LEN - self.buffer_len);
+            self.buffer[self.buffer_len..self.buffer_len + len].copy_from_slice(&bytes[..len]);
+            self.buffer_len += len;
+            bytes = &bytes[len..];
+            if self.buffer_len < STRIPE_LEN {
+                return;
+            }
+            Self::consume(&mut self.accs, &self.buffer);
+            self.buffer_len = 0;
+        }
+        let mut stripes = bytes.chunks_exact(STRIPE_LEN);
+        for stripe in &mut stripes {
+            Self::consume(&mut self.accs, stripe);
+        }
+        let remainder = stripes.remainder();
+        self.buffer[..remainder.len()].copy_from_slice(remainder);
+        self.buffer_len = remainder.len();
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        let mut h = if self.total_len >= STRIPE_LEN as u64 {
+            let [v1, v2, v3, v4] = self.accs;
+            let h = v1
+                .rotate_left(1)
+                .wrapping_add(v2.rotate_left(7))
+                .wrapping_add(v3.rotate_left(12))
+                .wrapping_add(v4.rotate_left(18));
+            self.accs.iter().fold(h, |h, acc| merge_round(h, *acc))
+        } else {
+            self.seed.wrapping_add(PRIME_5)
+        };
+        h = h.wrapping_add(self.total_len);
+
+        let mut remainder = &self.buffer[..self.buffer_len];
+        while remainder.len() >= 8 {
+            h = (h ^ round(0, read_u64(remainder)))
+                .rotate_left(27)
+                .wrapping_mul(PRIME_1)
+                .wrapping_add(PRIME_4);
+            remainder = &remainder[8..];
+        }
+        if remainder.len() >= 4 {
+            h = (h ^ u64::from(read_u32(remainder)).wrapping_mul(PRIME_1))
+                .rotate_left(23)
+                .wrapping_mul(PRIME_2)
+                .wrapping_add(PRIME_3);
+            remainder = &remainder[4..];
+        }
+        for byte in remainder {
+            h = (h ^ u64::from(*byte).wrapping_mul(PRIME_5))
+                .rotate_left(11)
+                .wrapping_mul(PRIME_1);
+        }
+
+        h ^= h >> 33;
+        h = h.wrapping_mul(PRIME_2);
+        h ^= h >> 29;
+        h = h.wrapping_mul(PRIME_3);
+        h ^ (h >> 32)
+    }
+
+    #[inline]
+    fn write_u8(&mut self, i: u8) {
+        self.write(&[i]);
+    }
+
+    #[inline]
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    #[inline]
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as i64 as u64);
+    }
+}
+
+/// Hashes raw bytes with xxHash64 and a zero seed, as done by
+/// [`HyperLogLog::insert_bytes`](crate::HyperLogLog::insert_bytes).
+#[inline]
+pub(crate) fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = XxHash64::with_seed(0);
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// A [`BuildHasher`] creating [`XxHash64`] hashers with a given seed.
+///
+/// This is the default hasher builder of [`HyperLogLog`](crate::HyperLogLog), with a
+/// zero seed. As its algorithm is frozen, hyperloglogs filled with fixed-width integers,
+/// or through [`insert_bytes`](crate::HyperLogLog::insert_bytes) or
+/// [`insert_hash`](crate::HyperLogLog::insert_hash), may be merged across platforms and
+/// Rust releases as long as they use the same seed. See [`XxHash64`] for other types.
+///
+/// # Examples
+///
+/// ```
+/// use hyperloglog::{BuildXxHash64, HyperLogLog};
+///
+/// let mut hll1 = HyperLogLog::<i32>::new();
+/// hll1.insert(&1);
+/// let mut hll2 = HyperLogLog::<i32, _>::with_hasher(BuildXxHash64::with_seed(0));
+/// hll2.insert(&1);
+/// assert_eq!(hll1.histogram(), hll2.histogram());
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct BuildXxHash64 {
+    seed: u64,
+}
+
+impl BuildXxHash64 {
+    /// Creates a hasher builder with the given seed.
+    #[inline]
+    #[must_use]
+    pub const fn with_seed(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Returns the seed of the hashers.
+    #[inline]
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl BuildHasher for BuildXxHash64 {
+    type Hasher = XxHash64;
+
+    #[inline]
+    fn build_hasher(&self) -> XxHash64 {
+        XxHash64::with_seed(self.seed)
+    }
+}
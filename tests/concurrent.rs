@@ -0,0 +1,72 @@
+#![cfg(feature = "std")]
+
+use std::thread;
+
+use hyperloglog::{
+    ConcurrentHyperLogLog, HyperLogLog, InlineHyperLogLog, InlineRegisters, Precision,
+    RegisterLayout,
+};
+
+#[test]
+fn insert_from_threads() {
+    let concurrent = ConcurrentHyperLogLog::<u32>::new();
+    thread::scope(|s| {
+        for t in 0..8 {
+            let concurrent = &concurrent;
+            s.spawn(move || {
+                // Threads insert overlapping ranges.
+                for i in t * 10_000..(t + 2) * 10_000 {
+                    concurrent.insert(&i);
+                }
+            });
+        }
+    });
+    let mut hll = HyperLogLog::<u32>::new();
+    hll.extend(0..90_000);
+    assert_eq!(concurrent.histogram(), hll.histogram());
+    assert_eq!(concurrent.len(), hll.len());
+}
+
+#[test]
+fn conversions() {
+    let mut hll = HyperLogLog::<u32>::with_precision(Precision::P10);
+    hll.extend(0..50_000);
+    let concurrent = ConcurrentHyperLogLog::from(hll.clone());
+    assert_eq!(concurrent.precision(), Precision::P10);
+    assert_eq!(concurrent.histogram(), hll.histogram());
+
+    concurrent.insert(&50_000);
+    hll.insert(&50_000);
+    let snapshot: HyperLogLog<u32> = concurrent.snapshot();
+    assert_eq!(snapshot.histogram(), hll.histogram());
+    let inline: InlineHyperLogLog<u32, 10> = concurrent.try_snapshot().unwrap();
+    assert_eq!(inline.histogram(), hll.histogram());
+    assert!(concurrent.try_snapshot::<InlineRegisters<12>>().is_none());
+    let converted: HyperLogLog<u32> = concurrent.into();
+    assert_eq!(converted.histogram(), hll.histogram());
+
+    let packed = hll.clone().with_layout(RegisterLayout::Packed);
+    let concurrent = ConcurrentHyperLogLog::from(packed);
+    assert_eq!(concurrent.histogram(), hll.histogram());
+}
+
+#[test]
+fn merge() {
+    let lhs = ConcurrentHyperLogLog::<u32>::new();
+    let rhs = ConcurrentHyperLogLog::<u32>::new();
+    for i in 0..60_000 {
+        lhs.insert(&i);
+        rhs.insert(&(i + 40_000));
+    }
+    lhs.try_merge_from(&rhs).unwrap();
+    let mut all = HyperLogLog::<u32>::new();
+    all.extend(0..100_000);
+    assert_eq!(lhs.histogram(), all.histogram());
+
+    let other = ConcurrentHyperLogLog::<u32>::with_precision(Precision::P8);
+    assert!(lhs.try_merge_from(&other).is_err());
+
+    let mut lhs = lhs;
+    lhs.clear();
+    assert!(lhs.is_empty());
+}
@@ -0,0 +1,257 @@
+#![cfg(feature = "alloc")]
+
+use hyperloglog::{
+    Classic, EstimationStrategy, Estimator, HyperLogLog, HyperLogLogPlusPlus, Improved,
+    MaximumLikelihood, Precision,
+};
+
+fn relative_error(precision: Precision, cardinality: u64) -> f64 {
+    relative_error_with(HyperLogLogPlusPlus, precision, cardinality)
+}
+
+fn relative_error_with<E: Estimator>(estimator: E, precision: Precision, cardinality: u64) -> f64 {
+    let mut hll = HyperLogLog::<u64>::with_precision(precision).with_estimator(estimator);
+    hll.extend(0..cardinality);
+    (hll.len() as f64 - cardinality as f64).abs() / (cardinality as f64)
+}
+
+#[test]
+fn bias_correction_range() {
+    // The bias hump sits between linear counting and the raw estimate.
+    for cardinality in [1_000, 5_000, 10_000, 20_000, 50_000, 100_000] {
+        let error = relative_error(Precision::P12, cardinality);
+        assert!(error < 0.05, "cardinality {cardinality}: error {error}");
+    }
+}
+
+#[test]
+fn all_precisions() {
+    for precision in Precision::variants() {
+        let m = (1u64 << precision.get()) as f64;
+        let tolerance = 4. * 1.04 / m.sqrt();
+        for cardinality in [10, 100, 1_000, 10_000, 100_000] {
+            let error = relative_error(*precision, cardinality);
+            assert!(
+                error < tolerance.max(0.1),
+                "precision {precision}, cardinality {cardinality}: error {error}"
+            );
+        }
+    }
+}
+
+#[test]
+fn improved() {
+    for precision in Precision::variants() {
+        let m = (1u64 << precision.get()) as f64;
+        let tolerance = 4. * 1.04 / m.sqrt();
+        for cardinality in [1, 10, 100, 1_000, 10_000, 100_000] {
+            let error = relative_error_with(Improved, *precision, cardinality);
+            assert!(
+                error < tolerance.max(0.1),
+                "precision {precision}, cardinality {cardinality}: error {error}"
+            );
+        }
+    }
+}
+
+#[test]
+fn improved_empty() {
+    let hll = HyperLogLog::<u64>::new().with_estimator(Improved);
+    assert_eq!(hll.len(), 0);
+}
+
+#[test]
+fn maximum_likelihood() {
+    for precision in Precision::variants() {
+        let m = (1u64 << precision.get()) as f64;
+        let tolerance = 4. * 1.04 / m.sqrt();
+        for cardinality in [1, 10, 100, 1_000, 10_000, 100_000] {
+            let error = relative_error_with(MaximumLikelihood, *precision, cardinality);
+            assert!(
+                error < tolerance.max(0.1),
+                "precision {precision}, cardinality {cardinality}: error {error}"
+            );
+        }
+    }
+}
+
+#[test]
+fn maximum_likelihood_iterations() {
+    let mut hll = HyperLogLog::<u64>::new();
+    let estimate = hll.maximum_likelihood_estimate();
+    assert_eq!(estimate.estimate(), 0.);
+    assert_eq!(estimate.iterations(), 0);
+
+    hll.extend(0..10_000);
+    let estimate = hll.maximum_likelihood_estimate();
+    assert!((9_000. ..11_000.).contains(&estimate.estimate()));
+    assert!((1..100).contains(&estimate.iterations()));
+}
+
+#[test]
+fn classic() {
+    // The classic estimator is only accurate outside of the bias hump.
+    for cardinality in [10, 100, 1_000, 100_000, 1_000_000] {
+        let error = relative_error_with(Classic, Precision::P12, cardinality);
+        assert!(error < 0.1, "cardinality {cardinality}: error {error}");
+    }
+}
+
+#[test]
+fn estimation_strategy() {
+    let mut hll = HyperLogLog::<u64>::new();
+    hll.extend(0..10_000);
+    for (strategy, expected) in [
+        (EstimationStrategy::Classic, hll.estimate_with(&Classic)),
+        (
+            EstimationStrategy::HyperLogLogPlusPlus,
+            hll.estimate_with(&HyperLogLogPlusPlus),
+        ),
+        (EstimationStrategy::Improved, hll.estimate_with(&Improved)),
+        (
+            EstimationStrategy::MaximumLikelihood,
+            hll.estimate_with(&MaximumLikelihood),
+        ),
+    ] {
+        hll.set_estimator(strategy);
+        assert_eq!(hll.len(), expected.round() as usize);
+    }
+}
+
+#[test]
+fn confidence_interval_coverage() {
+    let num_trials = 200;
+    let cardinality = 20_000;
+    let covered = (0..num_trials)
+        .filter(|trial| {
+            let mut hll = HyperLogLog::<u64>::with_precision(Precision::P10);
+            hll.extend(trial * cardinality..(trial + 1) * cardinality);
+            let estimate = hll.estimate(0.95);
+            (estimate.lower_bound()..=estimate.upper_bound()).contains(&(cardinality as f64))
+        })
+        .count();
+    let coverage = covered as f64 / num_trials as f64;
+    assert!(coverage > 0.88, "coverage {coverage}");
+}
+
+#[test]
+fn confidence_interval_width() {
+    let mut hll = HyperLogLog::<u64>::new();
+    hll.extend(0..100_000);
+    let narrow = hll.estimate(0.5);
+    let wide = hll.estimate(0.99);
+    assert_eq!(narrow.value(), wide.value());
+    assert_eq!(narrow.standard_error(), wide.standard_error());
+    assert!(wide.lower_bound() < narrow.lower_bound());
+    assert!(narrow.upper_bound() < wide.upper_bound());
+    // About 1.04 / sqrt(4096).
+    assert!((0.016..0.017).contains(&narrow.relative_standard_error()));
+    let estimate = hll.estimate(0.95);
+    let z = (estimate.upper_bound() - estimate.value()) / estimate.standard_error();
+    assert!((z - 1.96).abs() < 1e-3);
+}
+
+#[test]
+fn relative_standard_error_per_estimator() {
+    let m: f64 = 4096.;
+    let asymptotic = 1.0389 / m.sqrt();
+    let histogram = |cardinality: u64| {
+        let mut hll = HyperLogLog::<u64>::with_precision(Precision::P12);
+        hll.extend(0..cardinality);
+        hll.histogram()
+    };
+
+    // Every estimator reaches the asymptotic error for large cardinalities.
+    let large = histogram(100_000);
+    for estimator in [
+        EstimationStrategy::Classic,
+        EstimationStrategy::HyperLogLogPlusPlus,
+        EstimationStrategy::Improved,
+        EstimationStrategy::MaximumLikelihood,
+    ] {
+        let error = estimator.relative_standard_error(&large);
+        assert!((error - asymptotic).abs() < 1e-4, "{estimator:?}: {error}");
+    }
+
+    // Linear counting is more accurate for small cardinalities.
+    let small = histogram(100);
+    let error = HyperLogLogPlusPlus.relative_standard_error(&small);
+    assert!(error < 0.75 * asymptotic, "{error}");
+    assert_eq!(Classic.relative_standard_error(&small), error);
+    assert_eq!(Improved.relative_standard_error(&small), error);
+    assert_eq!(MaximumLikelihood.relative_standard_error(&small), error);
+
+    // Classic keeps linear counting up to 2.5 * m, where it is less accurate.
+    let medium = histogram(9_000);
+    let error = Classic.relative_standard_error(&medium);
+    assert!(error > 1.04 * asymptotic, "{error}");
+    for error in [
+        HyperLogLogPlusPlus.relative_standard_error(&medium),
+        Improved.relative_standard_error(&medium),
+    ] {
+        assert!((error - asymptotic).abs() < 1e-4, "{error}");
+    }
+}
+
+#[test]
+fn relative_standard_error_small_range() {
+    // The error of linear counting matches the observed one.
+    let num_trials = 300;
+    let cardinality = 200;
+    let (mut error, mut expected) = (0., 0.);
+    for trial in 0..num_trials {
+        let mut hll = HyperLogLog::<u64>::with_precision(Precision::P10);
+        hll.extend(trial * cardinality..(trial + 1) * cardinality);
+        let estimate = hll.estimate(0.95);
+        error += (estimate.value() / cardinality as f64 - 1.).powi(2);
+        expected += estimate.relative_standard_error();
+    }
+    let error = (error / num_trials as f64).sqrt();
+    let expected = expected / num_trials as f64;
+    assert!(expected < 0.75 * 1.04 / 32., "{expected}");
+    assert!(
+        (0.7..1.4).contains(&(error / expected)),
+        "{error} vs {expected}"
+    );
+}
+
+#[test]
+#[should_panic]
+fn confidence_out_of_range() {
+    HyperLogLog::<u64>::new().estimate(1.);
+}
+
+#[test]
+fn hip() {
+    let num_trials = 100;
+    let cardinality = 10_000;
+    let (mut hip_error, mut registers_error) = (0., 0.);
+    for trial in 0..num_trials {
+        let mut hll = HyperLogLog::<u64>::with_precision(Precision::P10).with_hip();
+        hll.extend(trial * cardinality..(trial + 1) * cardinality);
+        let truth = cardinality as f64;
+        hip_error += (hll.len() as f64 / truth - 1.).powi(2);
+        registers_error += (hll.estimate_with(&HyperLogLogPlusPlus) / truth - 1.).powi(2);
+    }
+    assert!(hip_error < registers_error);
+    let relative_standard_error = (hip_error / num_trials as f64).sqrt();
+    assert!(relative_standard_error < 2. * 0.83 / 32.);
+}
+
+#[test]
+fn hip_fallback() {
+    let mut hll = HyperLogLog::<u64>::new().with_hip();
+    hll.extend(0..1_000);
+    hll.clear();
+    assert!(hll.is_hip_active());
+    assert_eq!(hll.len(), 0);
+    hll.extend(0..1_000);
+    let other = hll.clone();
+    hll.try_merge_from(&other).unwrap();
+    assert!(!hll.is_hip_active());
+    assert_eq!(
+        hll.len(),
+        hll.estimate_with(hll.estimator()).round() as usize
+    );
+    assert!(!hll.try_merge(&other).unwrap().is_hip_active());
+}
@@ -0,0 +1,367 @@
+#![cfg(feature = "alloc")]
+
+use std::{collections::hash_map::RandomState, hash::Hasher};
+
+use hyperloglog::{HyperLogLog, Precision, RegisterLayout, XxHash64};
+
+#[test]
+fn merge_sparse_and_dense() {
+    let mut small = HyperLogLog::<u32>::new();
+    small.extend(0..50);
+    let mut large = HyperLogLog::<u32>::new();
+    large.extend(25..100_000);
+    let mut all = HyperLogLog::<u32>::new();
+    all.extend(0..100_000);
+
+    assert_eq!(small.try_merge(&large).unwrap().len(), all.len());
+    assert_eq!(large.try_merge(&small).unwrap().len(), all.len());
+
+    let mut merged = small.clone();
+    merged.try_merge_from(&large).unwrap();
+    assert_eq!(merged.len(), all.len());
+}
+
+#[test]
+fn merge_sparse() {
+    for precision in Precision::variants() {
+        let mut hll1 = HyperLogLog::<u32>::with_precision(*precision);
+        hll1.extend(0..750);
+        let mut hll2 = HyperLogLog::<u32>::with_precision(*precision);
+        hll2.extend(250..1_000);
+        let mut all = HyperLogLog::<u32>::with_precision(*precision);
+        all.extend(0..1_000);
+        assert_eq!(hll1.try_merge(&hll2).unwrap().len(), all.len());
+    }
+}
+
+#[test]
+fn clear_dense() {
+    let mut hll = HyperLogLog::<u32>::new();
+    hll.extend(0..100_000);
+    hll.clear();
+    assert!(hll.is_empty());
+    assert_eq!(hll.len(), 0);
+    hll.insert(&1);
+    assert_eq!(hll.len(), 1);
+}
+
+#[test]
+fn histogram_after_merge() {
+    for precision in Precision::variants() {
+        let mut sparse = HyperLogLog::<u32>::with_precision(*precision);
+        sparse.extend(0..20);
+        let mut dense = HyperLogLog::<u32>::with_precision(*precision);
+        dense.extend(10..50_000);
+        let mut all = HyperLogLog::<u32>::with_precision(*precision);
+        all.extend(0..50_000);
+
+        let merged = sparse.try_merge(&dense).unwrap();
+        assert_eq!(merged.histogram(), all.histogram());
+        let merged = dense.try_merge(&sparse).unwrap();
+        assert_eq!(merged.histogram(), all.histogram());
+    }
+}
+
+#[test]
+fn packed_layout() {
+    for precision in Precision::variants() {
+        let mut byte = HyperLogLog::<u32>::with_precision(*precision);
+        let mut packed =
+            HyperLogLog::<u32>::with_precision(*precision).with_layout(RegisterLayout::Packed);
+        for i in 0..50_000 {
+            byte.insert(&i);
+            packed.insert(&i);
+            if i % 10_000 == 0 {
+                assert_eq!(packed.histogram(), byte.histogram());
+            }
+        }
+        assert_eq!(packed.histogram(), byte.histogram());
+        assert_eq!(packed.len(), byte.len());
+
+        packed.clear();
+        assert!(packed.is_empty());
+        assert_eq!(packed.layout(), RegisterLayout::Packed);
+    }
+}
+
+#[test]
+fn compact_layout() {
+    for precision in Precision::variants() {
+        let mut byte = HyperLogLog::<u32>::with_precision(*precision);
+        let mut compact =
+            HyperLogLog::<u32>::with_precision(*precision).with_layout(RegisterLayout::Compact);
+        for i in 0..50_000 {
+            byte.insert(&i);
+            compact.insert(&i);
+            if i % 10_000 == 0 {
+                assert_eq!(compact.histogram(), byte.histogram());
+            }
+        }
+        assert_eq!(compact.histogram(), byte.histogram());
+        assert_eq!(compact.len(), byte.len());
+
+        compact.clear();
+        assert!(compact.is_empty());
+        assert_eq!(compact.layout(), RegisterLayout::Compact);
+    }
+}
+
+#[test]
+fn compact_layout_exceptions() {
+    let mut byte = HyperLogLog::<u32>::with_precision(Precision::P4);
+    byte.extend(0..1_000);
+    let mut compact = byte.clone().with_layout(RegisterLayout::Compact);
+    // Register values far above all others, which do not fit on 4 bits.
+    for (j, shift) in [(0, 4), (1, 10), (2, 39), (2, 30)] {
+        let h = (1 << shift) | j;
+        byte.insert_hash(h);
+        compact.insert_hash(h);
+    }
+    assert_eq!(compact.histogram(), byte.histogram());
+    // Raises all registers, so that the offset catches up with the exceptions.
+    for i in 1_000..2_000_000 {
+        byte.insert(&i);
+        compact.insert(&i);
+    }
+    assert_eq!(compact.histogram(), byte.histogram());
+    compact.set_layout(RegisterLayout::Byte);
+    assert_eq!(compact.histogram(), byte.histogram());
+}
+
+#[test]
+fn merge_layouts() {
+    let mut sparse = HyperLogLog::<u32>::new();
+    sparse.extend(0..50);
+    let mut byte = HyperLogLog::<u32>::new();
+    byte.extend(25..60_000);
+    let mut packed = HyperLogLog::<u32>::new().with_layout(RegisterLayout::Packed);
+    packed.extend(40_000..100_000);
+    let mut all = HyperLogLog::<u32>::new();
+    all.extend(0..100_000);
+
+    let merged = packed.try_merge(&byte).unwrap().try_merge(&sparse).unwrap();
+    assert_eq!(merged.layout(), RegisterLayout::Packed);
+    assert_eq!(merged.histogram(), all.histogram());
+    let merged = byte.try_merge(&packed).unwrap().try_merge(&sparse).unwrap();
+    assert_eq!(merged.layout(), RegisterLayout::Byte);
+    assert_eq!(merged.histogram(), all.histogram());
+    let merged = sparse
+        .clone()
+        .with_layout(RegisterLayout::Packed)
+        .try_merge(&byte)
+        .unwrap()
+        .try_merge(&packed)
+        .unwrap();
+    assert_eq!(merged.histogram(), all.histogram());
+
+    let mut compact = HyperLogLog::<u32>::new().with_layout(RegisterLayout::Compact);
+    compact.extend(10_000..90_000);
+    let merged = compact.try_merge(&merged).unwrap();
+    assert_eq!(merged.histogram(), all.histogram());
+    let merged = packed
+        .try_merge(&compact)
+        .unwrap()
+        .try_merge(&byte)
+        .unwrap();
+    let merged = merged.try_merge(&sparse).unwrap();
+    assert_eq!(merged.histogram(), all.histogram());
+
+    let mut converted = merged.clone();
+    converted.set_layout(RegisterLayout::Byte);
+    assert_eq!(converted.histogram(), all.histogram());
+}
+
+#[test]
+fn insert_hashes() {
+    let hashes: Vec<u64> = (0..100_000u64)
+        .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15).rotate_left(17))
+        .collect();
+    for layout in [
+        RegisterLayout::Byte,
+        RegisterLayout::Packed,
+        RegisterLayout::Compact,
+    ] {
+        for len in [0, 10, 1_000, 100_000] {
+            let mut batch = HyperLogLog::<u64, ()>::with_hasher(()).with_layout(layout);
+            batch.insert_hashes(&hashes[..len]);
+            let mut sequential = HyperLogLog::<u64, ()>::with_hasher(()).with_layout(layout);
+            for h in &hashes[..len] {
+                sequential.insert_hash(*h);
+            }
+            assert_eq!(batch.histogram(), sequential.histogram());
+        }
+    }
+}
+
+#[test]
+fn insert_many_with_hip() {
+    let mut batch = HyperLogLog::<u32>::new().with_hip();
+    batch.insert_many(0..10_000);
+    let mut sequential = HyperLogLog::<u32>::new().with_hip();
+    for i in 0..10_000 {
+        sequential.insert(&i);
+    }
+    assert!(batch.is_hip_active());
+    assert_eq!(batch.histogram(), sequential.histogram());
+    assert_eq!(batch.len(), sequential.len());
+}
+
+#[test]
+fn insert_bytes() {
+    let values: Vec<String> = (0..10_000).map(|i| format!("value-{i}")).collect();
+    let mut default = HyperLogLog::<str>::new();
+    let mut random = HyperLogLog::<str, _>::with_hasher(RandomState::new());
+    let mut hashed = HyperLogLog::<str>::new();
+    for value in &values {
+        default.insert_bytes(value.as_bytes());
+        random.insert_bytes(value.as_bytes());
+        let mut hasher = XxHash64::with_seed(0);
+        hasher.write(value.as_bytes());
+        hashed.insert_hash(hasher.finish());
+    }
+    assert_eq!(default.histogram(), hashed.histogram());
+    assert_eq!(random.histogram(), hashed.histogram());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn merge_hasher_identity() {
+    let hasher = RandomState::new();
+    let mut hll1 = HyperLogLog::<u32, _>::with_hasher(hasher.clone());
+    hll1.extend(0..1_000);
+    let mut hll2 = HyperLogLog::<u32, _>::with_hasher(hasher);
+    hll2.extend(500..1_500);
+    assert!(hll1.try_merge_from(&hll2).is_ok());
+
+    let hll3 = HyperLogLog::<u32, _>::with_hasher(RandomState::new());
+    let error = hll1.try_merge(&hll3).unwrap_err();
+    assert_eq!(error.kind(), hyperloglog::TryMergeErrorKind::Hasher);
+}
+
+#[test]
+fn reduce_precision() {
+    // Small hash values saturate registers, whose folded values depend on the index bits.
+    let hashes: Vec<u64> = (0..50_000u64)
+        .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15).rotate_left(17))
+        .chain(0..1 << 14)
+        .chain((0..64).map(|i| 1 << i))
+        .collect();
+    for layout in [
+        RegisterLayout::Byte,
+        RegisterLayout::Packed,
+        RegisterLayout::Compact,
+    ] {
+        let mut hll = HyperLogLog::<u64, ()>::with_precision_and_hasher(Precision::P14, ())
+            .with_layout(layout);
+        hll.insert_hashes(&hashes);
+        for precision in Precision::variants() {
+            if *precision > Precision::P14 {
+                continue;
+            }
+            let reduced = hll.reduce_precision(*precision);
+            assert_eq!(reduced.layout(), layout);
+            let mut expected = HyperLogLog::<u64, ()>::with_precision_and_hasher(*precision, ());
+            expected.insert_hashes(&hashes);
+            assert_eq!(reduced.to_bytes(), expected.to_bytes());
+        }
+    }
+}
+
+#[test]
+#[should_panic]
+fn reduce_to_higher_precision() {
+    let hll = HyperLogLog::<u32>::with_precision(Precision::P10);
+    let _ = hll.reduce_precision(Precision::P12);
+}
+
+#[test]
+fn merge_folding() {
+    let mut hll1 = HyperLogLog::<u32>::with_precision(Precision::P12);
+    hll1.extend(0..60_000);
+    let mut hll2 = HyperLogLog::<u32>::with_precision(Precision::P8);
+    hll2.extend(40_000..100_000);
+    let mut all = HyperLogLog::<u32>::with_precision(Precision::P8);
+    all.extend(0..100_000);
+
+    assert_eq!(
+        hll1.try_merge_folding(&hll2).unwrap().histogram(),
+        all.histogram()
+    );
+    assert_eq!(
+        hll2.try_merge_folding(&hll1).unwrap().histogram(),
+        all.histogram()
+    );
+    hll1.try_merge_from_folding(&hll2).unwrap();
+    assert_eq!(hll1.precision(), Precision::P8);
+    assert_eq!(hll1.histogram(), all.histogram());
+
+    let hll3 = HyperLogLog::<u32, _>::with_hasher(hyperloglog::BuildXxHash64::with_seed(1));
+    let hll4 = HyperLogLog::<u32, _>::with_hasher(hyperloglog::BuildXxHash64::with_seed(2));
+    let error = hll3.try_merge_folding(&hll4).unwrap_err();
+    assert_eq!(error.kind(), hyperloglog::TryMergeErrorKind::Hasher);
+}
+
+#[test]
+fn union_all() {
+    for (layout, precision) in [
+        (RegisterLayout::Byte, Precision::P12),
+        (RegisterLayout::Packed, Precision::P12),
+        (RegisterLayout::Compact, Precision::P12),
+        (RegisterLayout::Byte, Precision::P16),
+        (RegisterLayout::Compact, Precision::P16),
+    ] {
+        // Mix sparse and dense registers.
+        let hlls: Vec<_> = [10, 100, 50_000, 1_000, 200_000]
+            .iter()
+            .enumerate()
+            .map(|(i, &len)| {
+                let mut hll = HyperLogLog::<u32>::with_precision(precision).with_layout(layout);
+                let start = i as u32 * 100_000;
+                hll.extend(start..start + len);
+                hll
+            })
+            .collect();
+        let mut merged = hlls[0].clone();
+        for hll in &hlls[1..] {
+            merged.try_merge_from(hll).unwrap();
+        }
+
+        let union = HyperLogLog::union_all(&hlls).unwrap().unwrap();
+        assert_eq!(union.layout(), layout);
+        assert_eq!(union.histogram(), merged.histogram());
+        assert_eq!(HyperLogLog::union_len(&hlls).unwrap(), merged.len());
+        assert_eq!(
+            HyperLogLog::union_len(hlls.iter().rev()).unwrap(),
+            merged.len()
+        );
+    }
+}
+
+#[test]
+fn union_all_empty() {
+    let hlls: [HyperLogLog<u32>; 0] = [];
+    assert!(HyperLogLog::union_all(&hlls).unwrap().is_none());
+    assert_eq!(HyperLogLog::union_len(&hlls).unwrap(), 0);
+
+    let hll = HyperLogLog::<u32>::new();
+    let union = HyperLogLog::union_all([&hll, &hll]).unwrap().unwrap();
+    assert!(union.is_empty());
+    assert_eq!(HyperLogLog::union_len([&hll]).unwrap(), 0);
+}
+
+#[test]
+fn union_all_incompatible() {
+    let hll1 = HyperLogLog::<u32>::with_precision(Precision::P12);
+    let hll2 = HyperLogLog::<u32>::with_precision(Precision::P10);
+    let error = HyperLogLog::union_all([&hll1, &hll1, &hll2]).unwrap_err();
+    assert_eq!(error.kind(), hyperloglog::TryMergeErrorKind::Precision);
+    let error = HyperLogLog::union_len([&hll1, &hll1, &hll2]).unwrap_err();
+    assert_eq!(error.kind(), hyperloglog::TryMergeErrorKind::Precision);
+
+    let hll3 = HyperLogLog::<u32, _>::with_hasher(hyperloglog::BuildXxHash64::with_seed(1));
+    let hll4 = HyperLogLog::<u32, _>::with_hasher(hyperloglog::BuildXxHash64::with_seed(2));
+    let error = HyperLogLog::union_all([&hll3, &hll4]).unwrap_err();
+    assert_eq!(error.kind(), hyperloglog::TryMergeErrorKind::Hasher);
+    let error = HyperLogLog::union_len([&hll3, &hll4]).unwrap_err();
+    assert_eq!(error.kind(), hyperloglog::TryMergeErrorKind::Hasher);
+}
@@ -0,0 +1,94 @@
+#[cfg(feature = "alloc")]
+use hyperloglog::HyperLogLog;
+use hyperloglog::{BuildXxHash64, InlineHyperLogLog, Precision};
+
+#[cfg(feature = "alloc")]
+#[test]
+fn inline_registers() {
+    let mut inline = InlineHyperLogLog::<u32, 10>::new();
+    let mut default = HyperLogLog::<u32>::with_precision(Precision::P10);
+    assert_eq!(inline.precision(), Precision::P10);
+    assert!(inline.is_empty());
+    inline.extend(0..50_000);
+    default.extend(0..50_000);
+    assert_eq!(inline.histogram(), default.histogram());
+    assert_eq!(inline.len(), default.len());
+
+    let mut other = InlineHyperLogLog::<u32, 10>::new();
+    other.extend(25_000..100_000);
+    default.extend(25_000..100_000);
+    let merged = inline.try_merge(&other).unwrap();
+    assert_eq!(merged.histogram(), default.histogram());
+    inline.try_merge_from(&other).unwrap();
+    assert_eq!(inline.histogram(), default.histogram());
+
+    inline.clear();
+    assert!(inline.is_empty());
+}
+
+#[test]
+fn inline_with_precision() {
+    let hll = InlineHyperLogLog::<u32, 8>::with_precision(Precision::P8);
+    assert_eq!(hll.precision(), Precision::P8);
+}
+
+#[test]
+fn inline_with_hasher() {
+    let mut hll =
+        InlineHyperLogLog::<u32, 10, BuildXxHash64>::with_hasher(BuildXxHash64::with_seed(3));
+    assert_eq!(hll.precision(), Precision::P10);
+    assert_eq!(hll.hasher(), &BuildXxHash64::with_seed(3));
+    hll.extend(0..1_000);
+    assert!((900..1_100).contains(&hll.len()));
+}
+
+#[test]
+#[should_panic = "precision must be P8"]
+fn inline_with_wrong_precision() {
+    let _ = InlineHyperLogLog::<u32, 8>::with_precision(Precision::P10);
+}
+
+#[cfg(feature = "alloc")]
+fn check_merge<const P: u8>()
+where
+    hyperloglog::ConstPrecision<P>: hyperloglog::InlineArray,
+{
+    let precision = Precision::new(P).unwrap();
+    let mut inline = InlineHyperLogLog::<u32, P>::new();
+    let mut other = InlineHyperLogLog::<u32, P>::new();
+    let mut default = HyperLogLog::<u32>::with_precision(precision);
+    inline.extend(0..300);
+    other.extend(200..20_000);
+    default.extend(0..20_000);
+    inline.try_merge_from(&other).unwrap();
+    assert_eq!(inline.histogram(), default.histogram());
+    assert!(!inline.is_empty());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn inline_merge() {
+    check_merge::<4>();
+    check_merge::<5>();
+    check_merge::<6>();
+    check_merge::<14>();
+}
+
+#[test]
+fn inline_union() {
+    let mut hlls = [
+        InlineHyperLogLog::<u32, 12>::new(),
+        InlineHyperLogLog::<u32, 12>::new(),
+        InlineHyperLogLog::<u32, 12>::new(),
+    ];
+    for (i, hll) in hlls.iter_mut().enumerate() {
+        let start = i as u32 * 10_000;
+        hll.extend(start..start + 20_000);
+    }
+    let mut merged = hlls[0].clone();
+    merged.try_merge_from(&hlls[1]).unwrap();
+    merged.try_merge_from(&hlls[2]).unwrap();
+    let union = InlineHyperLogLog::union_all(&hlls).unwrap().unwrap();
+    assert_eq!(union.histogram(), merged.histogram());
+    assert_eq!(InlineHyperLogLog::union_len(&hlls).unwrap(), merged.len());
+}
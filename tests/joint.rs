@@ -0,0 +1,216 @@
+#![cfg(feature = "alloc")]
+
+use hyperloglog::{BuildXxHash64, Estimator, Histogram, HyperLogLog, Precision, TryMergeErrorKind};
+
+fn sketches(
+    precision: Precision,
+    lhs_only: u64,
+    rhs_only: u64,
+    both: u64,
+) -> (HyperLogLog<u64>, HyperLogLog<u64>) {
+    let mut lhs = HyperLogLog::<u64>::with_precision(precision);
+    let mut rhs = HyperLogLog::<u64>::with_precision(precision);
+    lhs.extend(0..lhs_only);
+    rhs.extend(1 << 32..(1 << 32) + rhs_only);
+    lhs.extend(1 << 48..(1 << 48) + both);
+    rhs.extend(1 << 48..(1 << 48) + both);
+    (lhs, rhs)
+}
+
+#[test]
+fn intersection() {
+    for (lhs_only, rhs_only, both) in [
+        (100_000, 100_000, 1_000),
+        (100_000, 100_000, 10_000),
+        (1_000_000, 10_000, 5_000),
+        (10_000, 10_000, 100_000),
+        (50, 70, 20),
+    ] {
+        let (lhs, rhs) = sketches(Precision::P12, lhs_only, rhs_only, both);
+        let estimate = lhs.try_intersection(&rhs, 0.999).unwrap();
+        assert!(
+            estimate.lower_bound() <= both as f64 && both as f64 <= estimate.upper_bound(),
+            "{lhs_only}, {rhs_only}, {both}: {estimate:?}"
+        );
+        let estimate = lhs
+            .try_intersection_inclusion_exclusion(&rhs, 0.999)
+            .unwrap();
+        assert!(
+            estimate.lower_bound() <= both as f64 && both as f64 <= estimate.upper_bound(),
+            "{lhs_only}, {rhs_only}, {both}: {estimate:?}"
+        );
+    }
+}
+
+#[test]
+fn intersection_small_overlap() {
+    // Joint estimation is much more accurate than inclusion–exclusion for small overlaps.
+    let (mut joint, mut inclusion_exclusion) = (0., 0.);
+    for i in 0..10 {
+        let mut lhs = HyperLogLog::<u64>::with_precision(Precision::P10);
+        let mut rhs = HyperLogLog::<u64>::with_precision(Precision::P10);
+        let base = i << 40;
+        lhs.extend(base..base + 100_000);
+        rhs.extend(base + 99_000..base + 199_000);
+        let error = |value: f64| (value - 1_000.).powi(2);
+        joint += error(lhs.try_intersection(&rhs, 0.95).unwrap().value());
+        inclusion_exclusion += error(
+            lhs.try_intersection_inclusion_exclusion(&rhs, 0.95)
+                .unwrap()
+                .value(),
+        );
+    }
+    assert!(
+        joint < inclusion_exclusion / 4.,
+        "{joint} >= {inclusion_exclusion} / 4"
+    );
+}
+
+#[test]
+fn intersection_disjoint() {
+    let (lhs, rhs) = sketches(Precision::P12, 100_000, 100_000, 0);
+    let estimate = lhs.try_intersection(&rhs, 0.99).unwrap();
+    assert!(estimate.value() < 2_000., "{estimate:?}");
+    assert_eq!(estimate.lower_bound(), 0.);
+}
+
+#[test]
+fn intersection_identical() {
+    let (lhs, _) = sketches(Precision::P12, 0, 0, 100_000);
+    let estimate = lhs.try_intersection(&lhs, 0.95).unwrap();
+    assert!((estimate.value() - 100_000.).abs() < 5_000., "{estimate:?}");
+    let estimate = lhs
+        .try_intersection_inclusion_exclusion(&lhs, 0.95)
+        .unwrap();
+    assert!((estimate.value() - 100_000.).abs() < 5_000., "{estimate:?}");
+}
+
+#[test]
+fn intersection_empty() {
+    let (lhs, rhs) = sketches(Precision::P12, 0, 1_000, 0);
+    for estimate in [
+        lhs.try_intersection(&rhs, 0.95).unwrap(),
+        rhs.try_intersection(&lhs, 0.95).unwrap(),
+        lhs.try_intersection(&lhs, 0.95).unwrap(),
+        lhs.try_intersection_inclusion_exclusion(&rhs, 0.95)
+            .unwrap(),
+    ] {
+        assert_eq!(estimate.value(), 0.);
+        assert!(estimate.upper_bound().is_finite());
+    }
+}
+
+#[test]
+fn joint_estimate() {
+    for (lhs_only, rhs_only, both) in [
+        (100_000, 100_000, 1_000),
+        (100_000, 50_000, 50_000),
+        (10_000, 1_000_000, 5_000),
+    ] {
+        let (lhs, rhs) = sketches(Precision::P12, lhs_only, rhs_only, both);
+        let estimate = lhs.try_joint_estimate(&rhs, 0.95).unwrap();
+        for (value, expected) in [
+            (estimate.lhs_difference(), lhs_only),
+            (estimate.rhs_difference(), rhs_only),
+            (estimate.intersection(), both),
+            (estimate.union(), lhs_only + rhs_only + both),
+        ] {
+            assert!(
+                (value.value() - expected as f64).abs() <= 5. * value.standard_error(),
+                "{lhs_only}, {rhs_only}, {both}: {value:?}"
+            );
+        }
+        let union = estimate.lhs_difference().value()
+            + estimate.rhs_difference().value()
+            + estimate.intersection().value();
+        assert!((estimate.union().value() - union).abs() < 1e-6 * union);
+
+        let jaccard = both as f64 / (lhs_only + rhs_only + both) as f64;
+        assert!(
+            (estimate.jaccard() - jaccard).abs() <= 4. * estimate.jaccard_standard_error(),
+            "{lhs_only}, {rhs_only}, {both}: {estimate:?}"
+        );
+    }
+}
+
+#[test]
+fn joint_estimate_symmetric() {
+    let (lhs, rhs) = sketches(Precision::P10, 20_000, 5_000, 3_000);
+    let estimate = lhs.try_joint_estimate(&rhs, 0.95).unwrap();
+    let reverse = rhs.try_joint_estimate(&lhs, 0.95).unwrap();
+    let close = |a: f64, b: f64| (a - b).abs() <= 1e-6 * a.max(b).max(1.);
+    assert!(close(
+        estimate.lhs_difference().value(),
+        reverse.rhs_difference().value()
+    ));
+    assert!(close(
+        estimate.intersection().value(),
+        reverse.intersection().value()
+    ));
+    assert!(close(estimate.jaccard(), reverse.jaccard()));
+}
+
+#[test]
+fn joint_estimate_empty() {
+    let hll = HyperLogLog::<u64>::new();
+    let estimate = hll.try_joint_estimate(&hll, 0.95).unwrap();
+    assert_eq!(estimate.union().value(), 0.);
+    assert_eq!(estimate.jaccard(), 0.);
+    assert_eq!(estimate.jaccard_standard_error(), 0.);
+}
+
+#[test]
+fn intersection_incompatible() {
+    let lhs = HyperLogLog::<u64>::with_precision(Precision::P10);
+    let rhs = HyperLogLog::<u64>::with_precision(Precision::P12);
+    assert_eq!(
+        lhs.try_intersection(&rhs, 0.95).unwrap_err().kind(),
+        TryMergeErrorKind::Precision
+    );
+    assert_eq!(
+        lhs.try_intersection_inclusion_exclusion(&rhs, 0.95)
+            .unwrap_err()
+            .kind(),
+        TryMergeErrorKind::Precision
+    );
+
+    let lhs = HyperLogLog::<u64, _>::with_hasher(BuildXxHash64::with_seed(1));
+    let rhs = HyperLogLog::<u64, _>::with_hasher(BuildXxHash64::with_seed(2));
+    assert_eq!(
+        lhs.try_joint_estimate(&rhs, 0.95).unwrap_err().kind(),
+        TryMergeErrorKind::Hasher
+    );
+    assert_eq!(
+        lhs.try_intersection(&rhs, 0.95).unwrap_err().kind(),
+        TryMergeErrorKind::Hasher
+    );
+    assert_eq!(
+        lhs.try_intersection_inclusion_exclusion(&rhs, 0.95)
+            .unwrap_err()
+            .kind(),
+        TryMergeErrorKind::Hasher
+    );
+}
+
+#[test]
+fn intersection_inclusion_exclusion_invalid_estimates() {
+    // Estimators returning negative or NaN estimates must not make the intersection panic.
+    #[derive(Clone)]
+    struct Constant(f64);
+
+    impl Estimator for Constant {
+        fn estimate(&self, _histogram: &Histogram) -> f64 {
+            self.0
+        }
+    }
+
+    for value in [-1_000., f64::NAN] {
+        let (lhs, rhs) = sketches(Precision::P10, 1_000, 1_000, 1_000);
+        let lhs = lhs.with_estimator(Constant(value));
+        let rhs = rhs.with_estimator(Constant(value));
+        let estimate = lhs
+            .try_intersection_inclusion_exclusion(&rhs, 0.95)
+            .unwrap();
+        assert_eq!(estimate.value(), 0., "{estimate:?}");
+    }
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "rayon")]
+
+use hyperloglog::{HyperLogLog, Precision, RegisterLayout, TryMergeErrorKind};
+use rayon::iter::{
+    IntoParallelIterator, IntoParallelRefIterator, ParallelExtend, ParallelIterator,
+};
+
+#[test]
+fn par_extend() {
+    let mut hll = HyperLogLog::<u32>::new();
+    hll.extend(0..10);
+    hll.par_extend(0..200_000);
+    let mut all = HyperLogLog::<u32>::new();
+    all.extend(0..200_000);
+    assert_eq!(hll.histogram(), all.histogram());
+
+    let values: Vec<u32> = (0..200_000).collect();
+    let mut hll = HyperLogLog::<u32>::new().with_layout(RegisterLayout::Packed);
+    hll.par_extend(values.par_iter());
+    assert_eq!(hll.histogram(), all.histogram());
+}
+
+#[test]
+fn from_par_iter() {
+    let hll: HyperLogLog<u32> = (0..100_000).into_par_iter().collect();
+    let mut all = HyperLogLog::<u32>::new();
+    all.extend(0..100_000);
+    assert_eq!(hll.histogram(), all.histogram());
+}
+
+#[test]
+fn par_extend_precisions() {
+    for precision in Precision::variants() {
+        let mut hll = HyperLogLog::<u32>::with_precision(*precision);
+        hll.par_extend(0..100_000);
+        let mut all = HyperLogLog::<u32>::with_precision(*precision);
+        all.extend(0..100_000);
+        assert_eq!(hll.histogram(), all.histogram());
+    }
+}
+
+#[test]
+fn try_par_merge_from() {
+    let hlls: Vec<_> = (0..25)
+        .map(|i| (i * 4_000..(i + 2) * 4_000).collect::<HyperLogLog<u32>>())
+        .collect();
+    let mut merged = HyperLogLog::<u32>::new().with_hip();
+    merged.try_par_merge_from(&[]).unwrap();
+    assert!(merged.is_hip_active());
+    merged.try_par_merge_from(&hlls).unwrap();
+    assert!(!merged.is_hip_active());
+    let mut all = HyperLogLog::<u32>::new();
+    all.extend(0..104_000);
+    assert_eq!(merged.histogram(), all.histogram());
+
+    let mut hlls = hlls;
+    hlls.push(HyperLogLog::with_precision(Precision::P10));
+    let mut hll = HyperLogLog::<u32>::new();
+    let error = hll.try_par_merge_from(&hlls).unwrap_err();
+    assert_eq!(error.kind(), TryMergeErrorKind::Precision);
+    assert!(hll.is_empty());
+}
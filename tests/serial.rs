@@ -0,0 +1,72 @@
+#![cfg(feature = "alloc")]
+
+use hyperloglog::{
+    BuildXxHash64, FromBytesErrorKind, HyperLogLog, InlineHyperLogLog, Precision, RegisterLayout,
+};
+
+#[test]
+fn round_trip() {
+    for precision in Precision::variants() {
+        for layout in [
+            RegisterLayout::Byte,
+            RegisterLayout::Packed,
+            RegisterLayout::Compact,
+        ] {
+            let mut hll = HyperLogLog::<u32>::with_precision(*precision).with_layout(layout);
+            hll.extend(0..10_000);
+            let bytes = hll.to_bytes();
+            assert_eq!(bytes.len(), 17 + (1 << precision.get()));
+            let copy = HyperLogLog::<u32>::from_bytes(&bytes, BuildXxHash64::default()).unwrap();
+            assert_eq!(copy.precision(), *precision);
+            assert_eq!(copy.histogram(), hll.histogram());
+        }
+    }
+}
+
+#[test]
+fn round_trip_inline() {
+    let mut hll = HyperLogLog::<u32>::with_precision(Precision::P10);
+    hll.extend(0..10_000);
+    let copy = InlineHyperLogLog::<u32, 10>::from_bytes(&hll.to_bytes(), BuildXxHash64::default())
+        .unwrap();
+    assert_eq!(copy.histogram(), hll.histogram());
+    assert_eq!(copy.to_bytes(), hll.to_bytes());
+
+    let hll = HyperLogLog::<u32>::with_precision(Precision::P12);
+    let error = InlineHyperLogLog::<u32, 10>::from_bytes(&hll.to_bytes(), BuildXxHash64::default())
+        .unwrap_err();
+    assert_eq!(error.kind(), FromBytesErrorKind::Precision);
+}
+
+#[test]
+fn invalid_bytes() {
+    let mut hll = HyperLogLog::<u32, _>::with_hasher(BuildXxHash64::with_seed(3));
+    hll.extend(0..10_000);
+    let bytes = hll.to_bytes();
+    let from_bytes = |bytes: &[u8]| {
+        HyperLogLog::<u32, _>::from_bytes(bytes, BuildXxHash64::with_seed(3)).map(|_| ())
+    };
+    assert!(from_bytes(&bytes).is_ok());
+
+    let invalid = |bytes: &[u8]| from_bytes(bytes).unwrap_err().kind();
+    assert_eq!(invalid(&bytes[..16]), FromBytesErrorKind::Invalid);
+    assert_eq!(
+        invalid(&bytes[..bytes.len() - 1]),
+        FromBytesErrorKind::Invalid
+    );
+    let mut corrupted = bytes.clone();
+    corrupted[0] = b'X';
+    assert_eq!(invalid(&corrupted), FromBytesErrorKind::Invalid);
+    let mut corrupted = bytes.clone();
+    corrupted[3] = 2;
+    assert_eq!(invalid(&corrupted), FromBytesErrorKind::Invalid);
+    let mut corrupted = bytes.clone();
+    corrupted[4] = 3;
+    assert_eq!(invalid(&corrupted), FromBytesErrorKind::Invalid);
+    let mut corrupted = bytes.clone();
+    corrupted[17] = 65;
+    assert_eq!(invalid(&corrupted), FromBytesErrorKind::Invalid);
+
+    let error = HyperLogLog::<u32>::from_bytes(&bytes, BuildXxHash64::default()).unwrap_err();
+    assert_eq!(error.kind(), FromBytesErrorKind::Hasher);
+}
@@ -0,0 +1,209 @@
+#![cfg(feature = "alloc")]
+
+use std::hash::BuildHasher;
+
+use hyperloglog::{
+    BuildXxHash64, EstimationStrategy, HyperLogLog, Precision, RegisterLayout, RegisterStorage,
+    Registers,
+};
+
+/// Dense registers relying on the default methods of [`RegisterStorage`].
+#[derive(Clone, Debug)]
+struct ByteRegisters {
+    precision: Precision,
+    values: Vec<u8>,
+}
+
+impl RegisterStorage for ByteRegisters {
+    fn with_precision(precision: Precision) -> Self {
+        let values = vec![0; 1 << precision.get()];
+        Self { precision, values }
+    }
+
+    fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    fn get(&self, index: usize) -> u8 {
+        self.values[index]
+    }
+
+    fn update(&mut self, index: usize, value: u8) -> u8 {
+        let previous = self.values[index];
+        self.values[index] = previous.max(value);
+        previous
+    }
+
+    fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        self.values.iter().copied()
+    }
+
+    fn clear(&mut self) {
+        self.values.fill(0);
+    }
+}
+
+type ByteHyperLogLog<T> = HyperLogLog<T, BuildXxHash64, EstimationStrategy, ByteRegisters>;
+
+#[test]
+fn custom_storage() {
+    for precision in Precision::variants() {
+        let mut custom = ByteHyperLogLog::<u32>::with_precision(*precision);
+        let mut default = HyperLogLog::<u32>::with_precision(*precision);
+        assert!(custom.is_empty());
+        custom.extend(0..10_000);
+        default.extend(0..10_000);
+        assert!(!custom.is_empty());
+        assert_eq!(custom.histogram(), default.histogram());
+        assert_eq!(custom.len(), default.len());
+
+        let mut other = ByteHyperLogLog::<u32>::with_precision(*precision);
+        other.extend(5_000..20_000);
+        default.extend(5_000..20_000);
+        custom.try_merge_from(&other).unwrap();
+        assert_eq!(custom.histogram(), default.histogram());
+
+        custom.clear();
+        assert!(custom.is_empty());
+    }
+}
+
+#[test]
+fn custom_storage_hip() {
+    let mut custom = ByteHyperLogLog::<u32>::with_precision(Precision::P14).with_hip();
+    let mut default = HyperLogLog::<u32>::with_precision(Precision::P14).with_hip();
+    custom.extend(0..50_000);
+    default.extend(0..50_000);
+    assert!(custom.is_hip_active());
+    assert_eq!(custom.len(), default.len());
+    assert_eq!(custom.estimate(0.95), default.estimate(0.95));
+
+    custom.clear();
+    default.clear();
+    custom.extend(0..1_000);
+    default.extend(0..1_000);
+    assert_eq!(custom.len(), default.len());
+}
+
+#[test]
+fn registers_get() {
+    for layout in [
+        RegisterLayout::Byte,
+        RegisterLayout::Packed,
+        RegisterLayout::Compact,
+    ] {
+        let mut registers = Registers::with_precision(Precision::P8);
+        registers.set_layout(layout);
+        for (index, value) in [(3, 5), (200, 2), (3, 4), (17, 40)] {
+            registers.update(index, value);
+        }
+        assert_eq!(registers.get(3), 5);
+        assert_eq!(registers.get(4), 0);
+        assert_eq!(registers.get(17), 40);
+        for index in 0..256 {
+            registers.update(index, 1);
+        }
+        let values: Vec<u8> = registers.iter().collect();
+        assert_eq!(values.len(), registers.len());
+        for (index, value) in values.iter().enumerate() {
+            assert_eq!(registers.get(index), *value);
+        }
+        assert_eq!(registers.get(200), 2);
+        assert_eq!(registers.get(17), 40);
+    }
+}
+
+#[test]
+fn sparse_registers() {
+    // Registers stay sparse up to a quarter of the registers at precision 18, buffering new
+    // entries before merging them.
+    let precision = Precision::P18;
+    let updates = |seed: u64, count: usize| {
+        (0..count as u64).map(move |i| {
+            let h = BuildXxHash64::with_seed(seed).hash_one(i);
+            ((h as usize) % (1 << 18), 1 + (h >> 59) as u8)
+        })
+    };
+    let mut registers = Registers::with_precision(precision);
+    let mut expected = ByteRegisters::with_precision(precision);
+    for (i, (index, value)) in updates(0, 50_000).enumerate() {
+        assert_eq!(
+            registers.update(index, value),
+            expected.update(index, value)
+        );
+        if i % 9_999 == 0 {
+            assert!(registers.iter().eq(expected.iter()));
+            assert_eq!(registers.histogram(), expected.histogram());
+        }
+    }
+    for (index, _) in updates(0, 1_000).chain(updates(1, 1_000)) {
+        assert_eq!(registers.get(index), expected.get(index));
+    }
+    for start in [0, 1_000, (1 << 18) - 100] {
+        let (mut values, mut expected_values) = ([1; 1_000], [1; 1_000]);
+        registers.max_into(start, &mut values);
+        expected.max_into(start, &mut expected_values);
+        assert_eq!(values, expected_values);
+    }
+
+    let mut other = Registers::with_precision(precision);
+    for (index, value) in updates(1, 10_000).chain(updates(0, 100)) {
+        other.update(index, value);
+        expected.update(index, value);
+    }
+    registers.merge_from_unchecked(&other);
+    assert!(registers.iter().eq(expected.iter()));
+    assert_eq!(registers.histogram(), expected.histogram());
+}
+
+#[test]
+fn merge_different_precisions() {
+    // Unchecked merges of different precisions only merge the registers found in both.
+    let updates = |seed: u64, precision: Precision, count: usize| {
+        (0..count as u64).map(move |i| {
+            let h = BuildXxHash64::with_seed(seed).hash_one(i);
+            ((h as usize) % (1 << precision.get()), 1 + (h >> 61) as u8)
+        })
+    };
+    let fill = |layout: RegisterLayout, precision: Precision, seed: u64, count: usize| {
+        let mut registers = Registers::with_precision(precision);
+        registers.set_layout(layout);
+        let mut expected = ByteRegisters::with_precision(precision);
+        for (index, value) in updates(seed, precision, count) {
+            registers.update(index, value);
+            expected.update(index, value);
+        }
+        (registers, expected)
+    };
+    for layout in [
+        RegisterLayout::Byte,
+        RegisterLayout::Packed,
+        RegisterLayout::Compact,
+    ] {
+        for (lhs_precision, rhs_precision) in [
+            (Precision::P8, Precision::P10),
+            (Precision::P10, Precision::P8),
+        ] {
+            for (lhs_count, rhs_count) in [(10, 10), (10, 5_000), (5_000, 10), (5_000, 5_000)] {
+                let (mut registers, mut expected) = fill(layout, lhs_precision, 0, lhs_count);
+                let (rhs, rhs_expected) = fill(layout, rhs_precision, 1, rhs_count);
+                registers.merge_from_unchecked(&rhs);
+                expected.merge_from_unchecked(&rhs_expected);
+                assert!(
+                    registers.iter().eq(expected.iter()),
+                    "{layout:?}, {lhs_precision:?}, {rhs_precision:?}, {lhs_count}, {rhs_count}"
+                );
+                assert_eq!(registers.histogram(), expected.histogram());
+            }
+        }
+    }
+
+    let mut lhs = HyperLogLog::<u64>::with_precision(Precision::P10);
+    let mut rhs = HyperLogLog::<u64>::with_precision(Precision::P12);
+    lhs.extend(0..10_000);
+    rhs.extend(0..10_000);
+    lhs.merge_from_unchecked(&rhs);
+    rhs.merge_from_unchecked(&lhs);
+    assert_eq!(lhs.precision(), Precision::P10);
+    assert_eq!(rhs.precision(), Precision::P12);
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "alloc")]
+
+use hyperloglog::{HyperLogLog, HyperLogLogMut, HyperLogLogRef, Precision, TryMergeErrorKind};
+
+#[test]
+fn insert_in_place() {
+    for precision in Precision::variants() {
+        let mut bytes = vec![0; 1 << precision.get()];
+        let mut view = HyperLogLogMut::<u32>::new(&mut bytes, *precision).unwrap();
+        assert!(view.is_empty());
+        view.extend(0..10_000);
+        let mut hll = HyperLogLog::<u32>::with_precision(*precision);
+        hll.extend(0..10_000);
+        assert_eq!(view.histogram(), hll.histogram());
+        assert_eq!(view.len(), hll.len());
+        assert_eq!(view.as_ref().len(), hll.len());
+        assert_eq!(view.estimate(0.95), hll.estimate(0.95));
+
+        let view = HyperLogLogRef::<u32>::new(&bytes, *precision).unwrap();
+        assert_eq!(view.histogram(), hll.histogram());
+        assert_eq!(view.len(), hll.len());
+    }
+}
+
+#[test]
+fn merge_into_owned() {
+    let mut bytes = vec![0; 1 << 12];
+    let mut view = HyperLogLogMut::<u32>::new(&mut bytes, Precision::P12).unwrap();
+    view.extend(0..60_000);
+    let mut hll = HyperLogLog::<u32>::new();
+    hll.extend(40_000..100_000);
+    let mut all = HyperLogLog::<u32>::new();
+    all.extend(0..100_000);
+
+    view.try_merge_into(&mut hll).unwrap();
+    assert_eq!(hll.histogram(), all.histogram());
+
+    let view = HyperLogLogRef::<u32>::new(&bytes, Precision::P12).unwrap();
+    let mut hll = HyperLogLog::<u32>::new().with_hip();
+    hll.extend(40_000..100_000);
+    view.try_merge_into(&mut hll).unwrap();
+    assert_eq!(hll.histogram(), all.histogram());
+    assert!(!hll.is_hip_active());
+
+    let mut hll = HyperLogLog::<u32>::with_precision(Precision::P10);
+    let error = view.try_merge_into(&mut hll).unwrap_err();
+    assert_eq!(error.kind(), TryMergeErrorKind::Precision);
+}
+
+#[test]
+fn invalid_slice() {
+    let bytes = vec![0; 1 << 10];
+    assert!(HyperLogLogRef::<u32>::new(&bytes, Precision::P10).is_ok());
+    assert!(HyperLogLogRef::<u32>::new(&bytes, Precision::P12).is_err());
+    let mut bytes = bytes;
+    bytes[3] = 56;
+    assert!(HyperLogLogRef::<u32>::new(&bytes, Precision::P10).is_err());
+    assert!(HyperLogLogMut::<u32>::new(&mut bytes, Precision::P10).is_err());
+    bytes[3] = 55;
+    assert!(HyperLogLogMut::<u32>::new(&mut bytes, Precision::P10).is_ok());
+}
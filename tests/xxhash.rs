@@ -0,0 +1,42 @@
+use core::hash::{BuildHasher, Hasher};
+
+use hyperloglog::{BuildXxHash64, XxHash64};
+
+fn xxh64(bytes: &[u8], seed: u64) -> u64 {
+    let mut hasher = XxHash64::with_seed(seed);
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+#[test]
+fn reference_values() {
+    let long: Vec<u8> = (0..100).collect();
+    assert_eq!(xxh64(b"", 0), 0xef46_db37_51d8_e999);
+    assert_eq!(xxh64(b"a", 0), 0xd24e_c4f1_a98c_6e5b);
+    assert_eq!(xxh64(b"abc", 0), 0x44bc_2cf5_ad77_0999);
+    assert_eq!(xxh64(b"hello world", 0), 0x45ab_6734_b21e_6968);
+    assert_eq!(xxh64(b"abc", 42), 0x13c1_d910_7027_70e6);
+    assert_eq!(xxh64(&long, 0), 0x6ac1_e580_3216_6597);
+}
+
+#[test]
+fn streaming() {
+    let bytes: Vec<u8> = (0..200).collect();
+    for len in [0, 1, 4, 8, 31, 32, 33, 64, 100, 200] {
+        for split in [0, 1, 5, 16, 31, 32, 40] {
+            let split = split.min(len);
+            let mut hasher = XxHash64::with_seed(7);
+            hasher.write(&bytes[..split]);
+            hasher.write(&bytes[split..len]);
+            assert_eq!(hasher.finish(), xxh64(&bytes[..len], 7));
+        }
+    }
+}
+
+#[test]
+fn portable_integers() {
+    let hash_builder = BuildXxHash64::default();
+    assert_eq!(hash_builder.hash_one(1u32), 0xf42f_9400_1fcb_5351);
+    assert_eq!(hash_builder.hash_one(1usize), hash_builder.hash_one(1u64));
+    assert_eq!(hash_builder.hash_one(-1isize), hash_builder.hash_one(-1i64));
+}